@@ -1,3 +1,4 @@
+mod automaton;
 mod common;
 mod coords;
 mod day01;
@@ -17,6 +18,8 @@ mod day14;
 mod day15;
 mod day16;
 mod grid;
+mod hex;
+mod search;
 
 type Runner = Box<dyn Fn(String, &[String])>;
 
@@ -0,0 +1,395 @@
+use super::coords::Coordinate;
+use super::grid::{Coord, GridStorage};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::iter;
+use std::marker::PhantomData;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+  Changed(usize),
+  FixedPoint,
+  Cycle { start: usize, length: usize },
+}
+
+type Affected<C> = Box<dyn Fn(C) -> Vec<C>>;
+
+pub struct Automaton<G, V, C = Coord> {
+  grid: G,
+  generation: usize,
+  last_changes: Option<Vec<C>>,
+  affected: Option<Affected<C>>,
+  history: Option<History<G>>,
+  value: PhantomData<V>,
+}
+
+// Every generation so far, indexed by a hash of its cells
+struct History<G> {
+  generations: Vec<G>,
+  by_hash: HashMap<u64, Vec<usize>>,
+}
+
+impl<G, V, C> Automaton<G, V, C>
+where
+  G: GridStorage<V, C> + Clone,
+  V: Clone + Eq + Hash,
+  C: Coordinate + 'static,
+{
+  pub fn new(grid: G) -> Self {
+    Self {
+      grid,
+      generation: 0,
+      last_changes: None,
+      affected: None,
+      history: None,
+      value: PhantomData,
+    }
+  }
+
+  // Stop with `Outcome::Cycle` when the grid returns to an earlier state.
+  // This hashes every cell each generation and keeps a copy of every
+  // generation, so it costs far more than the steps themselves when only a
+  // few cells change. Without it, `run` only stops at a fixed point.
+  #[allow(dead_code)]
+  pub fn detect_cycles(mut self) -> Self {
+    let mut by_hash = HashMap::new();
+    by_hash.insert(state_hash(&self.grid), vec![self.generation]);
+    self.history = Some(History {
+      generations: vec![self.grid.clone()],
+      by_hash,
+    });
+    self
+  }
+
+  // After the first step, only re-evaluate the cells that `affected` says a
+  // changed cell can influence (usually its neighbors and itself). Rules that
+  // look further than that, like line-of-sight rules, must say so here.
+  pub fn only_near_changes<F>(mut self, affected: F) -> Self
+  where
+    F: Fn(C) -> Vec<C> + 'static,
+  {
+    self.affected = Some(Box::new(affected));
+    self
+  }
+
+  #[allow(dead_code)]
+  pub fn grid(&self) -> &G {
+    &self.grid
+  }
+
+  pub fn into_grid(self) -> G {
+    self.grid
+  }
+
+  #[allow(dead_code)]
+  pub fn generation(&self) -> usize {
+    self.generation
+  }
+
+  // `rule` gives a cell's next value, or `None` to leave it alone. Only values
+  // that differ from the current one count as changes.
+  pub fn step<F>(&mut self, rule: F) -> Outcome
+  where
+    F: Fn(&G, C, &V) -> Option<V>,
+  {
+    let candidates: Vec<C> = match (&self.affected, &self.last_changes) {
+      (Some(affected), Some(changes)) => changes
+        .iter()
+        .flat_map(|&coord| affected(coord))
+        .map(|coord| stored_coord(&self.grid, coord))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect(),
+      _ => all_candidates(&self.grid),
+    };
+
+    let changes = changed_cells(&self.grid, candidates, &rule);
+    if changes.is_empty() {
+      return Outcome::FixedPoint;
+    }
+
+    let count = changes.len();
+    for (coord, value) in changes.iter() {
+      self.grid.insert(*coord, value.clone());
+    }
+    self.generation += 1;
+    self.last_changes = Some(changes.into_iter().map(|(coord, _)| coord).collect());
+
+    if let Some(History {
+      generations,
+      by_hash,
+    }) = &mut self.history
+    {
+      let grid = &self.grid;
+      let earlier = by_hash.entry(state_hash(grid)).or_default();
+      // Hashes can collide, so only a grid with the same cells counts
+      let repeated = earlier
+        .iter()
+        .find(|&&generation| same_cells(&generations[generation], grid));
+      if let Some(&start) = repeated {
+        return Outcome::Cycle {
+          start,
+          length: self.generation - start,
+        };
+      }
+      earlier.push(self.generation);
+      generations.push(grid.clone());
+    }
+
+    Outcome::Changed(count)
+  }
+
+  // Steps until the grid stops changing, or starts repeating itself if cycles
+  // are being detected
+  pub fn run<F>(&mut self, rule: F) -> Outcome
+  where
+    F: Fn(&G, C, &V) -> Option<V>,
+  {
+    loop {
+      match self.step(&rule) {
+        Outcome::Changed(_) => continue,
+        outcome => return outcome,
+      }
+    }
+  }
+}
+
+#[allow(dead_code)]
+pub fn next_generation<G, V, C, F>(grid: &G, rule: F) -> (G, usize)
+where
+  G: GridStorage<V, C> + Clone,
+  V: Clone + Eq,
+  C: Coordinate + 'static,
+  F: Fn(&G, C, &V) -> Option<V>,
+{
+  let candidates = all_candidates(grid);
+  let changes = changed_cells(grid, candidates, &rule);
+
+  let mut next = grid.clone();
+  for (coord, value) in changes.iter() {
+    next.insert(*coord, value.clone());
+  }
+
+  (next, changes.len())
+}
+
+// Every existing cell. A grid with a default value can grow, so the cells
+// next to existing ones are candidates too.
+fn all_candidates<G, V, C>(grid: &G) -> Vec<C>
+where
+  G: GridStorage<V, C>,
+  C: Coordinate + 'static,
+{
+  let existing: Vec<C> = grid.iter().map(|(coord, _)| coord).collect();
+  if grid.default_value().is_none() {
+    return existing;
+  }
+
+  existing
+    .into_iter()
+    .flat_map(|coord| coord.neighbors().chain(iter::once(coord)))
+    .map(|coord| stored_coord(grid, coord))
+    .collect::<HashSet<_>>()
+    .into_iter()
+    .collect()
+}
+
+// Across a wrapping edge, the cell on the other side. Anything past another
+// kind of edge is left where it is.
+fn stored_coord<G, V, C>(grid: &G, coord: C) -> C
+where
+  G: GridStorage<V, C>,
+  C: Coordinate,
+{
+  grid.locate(coord).unwrap_or(coord)
+}
+
+// Cells that don't exist yet, including any past the grid's extent, start out
+// as the grid's default value. Without a default they're never evaluated.
+fn changed_cells<G, V, C, F>(grid: &G, candidates: Vec<C>, rule: &F) -> Vec<(C, V)>
+where
+  G: GridStorage<V, C>,
+  V: Eq,
+  C: Coordinate,
+  F: Fn(&G, C, &V) -> Option<V>,
+{
+  let extent = grid.extent();
+  candidates
+    .into_iter()
+    .filter_map(|coord| {
+      let inside = extent.is_some_and(|bounds| bounds.contains(coord));
+      let current = match inside {
+        true => grid.get(coord).or_else(|| grid.default_value())?,
+        false => grid.default_value()?,
+      };
+      rule(grid, coord, current)
+        .filter(|value| value != current)
+        .map(|value| (coord, value))
+    })
+    .collect()
+}
+
+fn state_hash<G, V, C>(grid: &G) -> u64
+where
+  G: GridStorage<V, C>,
+  V: Hash,
+  C: Coordinate,
+{
+  let cells: Vec<_> = grid.iter().collect();
+
+  let mut hasher = DefaultHasher::new();
+  cells.hash(&mut hasher);
+  hasher.finish()
+}
+
+fn same_cells<G, V, C>(a: &G, b: &G) -> bool
+where
+  G: GridStorage<V, C>,
+  V: Eq,
+  C: Coordinate,
+{
+  a.iter().eq(b.iter())
+}
+
+#[test]
+fn test_automaton() {
+  use super::grid::{Grid, RowOrder};
+  use std::iter;
+
+  fn life(grid: &Grid<bool>, coord: Coord, &alive: &bool) -> Option<bool> {
+    let alive_neighbors = coord
+      .neighbors()
+      .filter(|c| grid.get(c) == Some(&true))
+      .count();
+
+    match (alive, alive_neighbors) {
+      (true, n) if n != 2 && n != 3 => Some(false),
+      (false, 3) => Some(true),
+      _ => None,
+    }
+  }
+
+  let parse = |map| Grid::from_str_with(map, RowOrder::YDown, |c| Ok::<_, ()>(c == '#')).unwrap();
+
+  let blinker: Grid<bool> = parse(".....\n..#..\n..#..\n..#..\n.....");
+  let (next, changes) = next_generation(&blinker, life);
+  assert_eq!(changes, 4);
+  assert_eq!(next.get((1, 2)), Some(&true));
+  assert_eq!(next.get((2, 1)), Some(&false));
+
+  let mut automaton = Automaton::new(blinker.clone()).detect_cycles();
+  assert_eq!(automaton.step(life), Outcome::Changed(4));
+  assert_eq!(automaton.generation(), 1);
+  assert_eq!(
+    automaton.run(life),
+    Outcome::Cycle {
+      start: 0,
+      length: 2
+    }
+  );
+
+  let mut automaton = Automaton::new(blinker.clone())
+    .detect_cycles()
+    .only_near_changes(|coord| coord.neighbors().chain(iter::once(coord)).collect());
+  assert_eq!(
+    automaton.run(life),
+    Outcome::Cycle {
+      start: 0,
+      length: 2
+    }
+  );
+
+  // Without cycle detection, stepping just carries on
+  let mut automaton = Automaton::new(blinker);
+  for _ in 0..5 {
+    assert_eq!(automaton.step(life), Outcome::Changed(4));
+  }
+
+  let block: Grid<bool> = parse("....\n.##.\n.##.\n....");
+  let mut automaton = Automaton::new(block.clone());
+  assert_eq!(automaton.run(life), Outcome::FixedPoint);
+  assert_eq!(automaton.generation(), 0);
+  assert_eq!(automaton.into_grid().to_string(), block.to_string());
+
+  // A rule can give every cell's next value, changed or not
+  let always = |grid: &Grid<bool>, coord: Coord, alive: &bool| {
+    Some(life(grid, coord, alive).unwrap_or(*alive))
+  };
+  let blinker: Grid<bool> = parse(".....\n..#..\n..#..\n..#..\n.....");
+  let (_, changes) = next_generation(&blinker, always);
+  assert_eq!(changes, 4);
+  let mut automaton = Automaton::new(block);
+  assert_eq!(automaton.run(always), Outcome::FixedPoint);
+  assert_eq!(automaton.generation(), 0);
+}
+
+#[test]
+fn test_automaton_wrapping() {
+  use super::grid::{Boundary, Grid, RowOrder};
+  use std::iter;
+
+  fn life(grid: &Grid<bool>, coord: Coord, &alive: &bool) -> Option<bool> {
+    let alive_neighbors = coord
+      .neighbors()
+      .filter(|c| grid.get(c) == Some(&true))
+      .count();
+    Some(alive_neighbors == 3 || (alive && alive_neighbors == 2))
+  }
+
+  // A glider on a torus, which crosses every edge on its way back to where
+  // it started after 32 generations
+  let mut grid: Grid<bool> = Grid::from_str_with(
+    ".#......\n..#.....\n###.....\n........\n........\n........\n........\n........",
+    RowOrder::YDown,
+    |c| Ok::<_, ()>(c == '#'),
+  )
+  .unwrap();
+  grid.set_boundary(0, Boundary::Wrap);
+  grid.set_boundary(1, Boundary::Wrap);
+
+  let mut automaton = Automaton::new(grid.clone())
+    .only_near_changes(|coord| coord.neighbors().chain(iter::once(coord)).collect());
+  let mut expected = grid.clone();
+  for _ in 0..32 {
+    automaton.step(life);
+    expected = next_generation(&expected, life).0;
+    assert!(same_cells(automaton.grid(), &expected));
+  }
+  assert!(same_cells(automaton.grid(), &grid));
+}
+
+#[test]
+fn test_automaton_in_3d() {
+  use super::grid::{Boundary, Grid, RowOrder};
+
+  type Coord3 = (isize, isize, isize);
+
+  fn life(grid: &Grid<bool, Coord3>, coord: Coord3, &alive: &bool) -> Option<bool> {
+    let alive_neighbors = coord
+      .neighbors()
+      .filter(|c| grid.get(c) == Some(&true))
+      .count();
+
+    match (alive, alive_neighbors) {
+      (true, n) if n != 2 && n != 3 => Some(false),
+      (false, 3) => Some(true),
+      _ => None,
+    }
+  }
+
+  // Space is unbounded, with every cell outside the seed starting out dead
+  let mut grid: Grid<bool, Coord3> =
+    Grid::from_str_with(".#.\n..#\n###", RowOrder::YDown, |c| Ok::<_, ()>(c == '#')).unwrap();
+  grid.set_default(false);
+  for axis in 0..3 {
+    grid.set_boundary(axis, Boundary::Infinite);
+  }
+
+  let mut automaton = Automaton::new(grid);
+  for _ in 0..6 {
+    automaton.step(life);
+  }
+  let active = automaton.grid().iter().filter(|(_, &alive)| alive).count();
+  assert_eq!(active, 112);
+}
@@ -1,4 +1,6 @@
-use strum::IntoEnumIterator;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::ops::{Add, Mul, Neg, Sub};
 use strum_macros::EnumIter;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
@@ -27,6 +29,7 @@ impl Direction {
     }
   }
 
+  #[allow(dead_code)]
   pub fn right_90(&self) -> Self {
     match self {
       Direction::N => Direction::E,
@@ -40,6 +43,7 @@ impl Direction {
     }
   }
 
+  #[allow(dead_code)]
   pub fn left_90(&self) -> Self {
     match self {
       Direction::N => Direction::W,
@@ -52,41 +56,348 @@ impl Direction {
       Direction::SE => Direction::NE,
     }
   }
+
+  // Positive degrees turn clockwise, negative counter-clockwise
+  pub fn rotate(&self, degrees: isize) -> Result<Self, String> {
+    if degrees % 45 != 0 {
+      return Err(format!("Cannot rotate a direction by {} degrees", degrees));
+    }
+
+    let index = CLOCKWISE.iter().position(|d| d == self).unwrap() as isize;
+    let steps = degrees / 45;
+    Ok(CLOCKWISE[(index + steps).rem_euclid(8) as usize])
+  }
+
+  #[allow(dead_code)]
+  pub fn opposite(&self) -> Self {
+    self.rotate(180).unwrap()
+  }
+
+  pub fn from_char(c: char) -> Result<Self, String> {
+    match c {
+      'N' => Ok(Direction::N),
+      'S' => Ok(Direction::S),
+      'E' => Ok(Direction::E),
+      'W' => Ok(Direction::W),
+      _ => Err(format!("Could not parse direction: {}", c)),
+    }
+  }
+
+  #[allow(dead_code)]
+  pub fn all_cardinal() -> [Direction; 4] {
+    [Direction::N, Direction::E, Direction::S, Direction::W]
+  }
 }
 
-pub trait Coordinate {
-  type Item;
+const CLOCKWISE: [Direction; 8] = [
+  Direction::N,
+  Direction::NE,
+  Direction::E,
+  Direction::SE,
+  Direction::S,
+  Direction::SW,
+  Direction::W,
+  Direction::NW,
+];
+
+// A point with any number of integer components. Tuples of two, three and four
+// `isize`s are coordinates; the first two components are always x and y.
+pub trait Coordinate: Copy + Eq + Hash + Ord + Default + Debug {
+  const DIMENSIONS: usize;
 
-  fn neighbors(&self) -> Vec<Self::Item>;
-  fn toward(&self, direction: Direction) -> TowardIter;
+  fn component(&self, axis: usize) -> isize;
+  fn map_components<F>(&self, f: F) -> Self
+  where
+    F: FnMut(usize, isize) -> isize;
+
+  fn neighbors(&self) -> NeighborIter<'static, Self> {
+    self.neighbors_with(Neighborhood::Moore)
+  }
+
+  fn neighbors_with<'a>(&self, kind: Neighborhood<'a, Self>) -> NeighborIter<'a, Self> {
+    NeighborIter::new(*self, kind)
+  }
+
+  // The point on the x/y plane through the origin
+  fn from_xy(x: isize, y: isize) -> Self {
+    Self::default().map_components(|axis, _| match axis {
+      0 => x,
+      1 => y,
+      _ => 0,
+    })
+  }
+}
+
+macro_rules! impl_coordinate {
+  ($dims:expr, $($idx:tt),+) => {
+    fn component(&self, axis: usize) -> isize {
+      match axis {
+        $($idx => self.$idx,)+
+        _ => panic!("No axis {} in a {}-dimensional coordinate", axis, $dims),
+      }
+    }
+
+    fn map_components<F>(&self, mut f: F) -> Self
+    where
+      F: FnMut(usize, isize) -> isize,
+    {
+      ($(f($idx, self.$idx),)+)
+    }
+  };
 }
 
 impl Coordinate for (isize, isize) {
-  type Item = (isize, isize);
+  const DIMENSIONS: usize = 2;
+  impl_coordinate!(2, 0, 1);
+}
+
+impl Coordinate for (isize, isize, isize) {
+  const DIMENSIONS: usize = 3;
+  impl_coordinate!(3, 0, 1, 2);
+}
 
-  fn neighbors(&self) -> Vec<Self::Item> {
-    let mut result: Vec<Self::Item> = Vec::with_capacity(8);
+impl Coordinate for (isize, isize, isize, isize) {
+  const DIMENSIONS: usize = 4;
+  impl_coordinate!(4, 0, 1, 2, 3);
+}
 
-    for dir in Direction::iter() {
-      let (dx, dy) = dir.delta();
-      result.push((self.0 + dx, self.1 + dy))
+#[derive(Debug, Clone, Copy)]
+#[allow(dead_code)]
+pub enum Neighborhood<'a, C> {
+  // Points one step away along a single axis
+  VonNeumann,
+  // Points at most one step away on every axis
+  Moore,
+  // Points exactly one step away on every axis
+  Diagonal,
+  // Points at each of the given offsets
+  Custom(&'a [C]),
+}
+
+const PLANAR_MOORE: [Direction; 8] = [
+  Direction::N,
+  Direction::S,
+  Direction::E,
+  Direction::W,
+  Direction::NW,
+  Direction::NE,
+  Direction::SW,
+  Direction::SE,
+];
+
+pub struct NeighborIter<'a, C> {
+  center: C,
+  kind: Neighborhood<'a, C>,
+  next: usize,
+}
+
+impl<'a, C> NeighborIter<'a, C> {
+  pub fn new(center: C, kind: Neighborhood<'a, C>) -> Self {
+    Self {
+      center,
+      kind,
+      next: 0,
     }
+  }
+}
 
-    result
+impl<'a, C: Coordinate> Iterator for NeighborIter<'a, C> {
+  type Item = C;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if let Neighborhood::Custom(offsets) = self.kind {
+      let offset = offsets.get(self.next)?;
+      self.next += 1;
+      return Some(
+        self
+          .center
+          .map_components(|axis, value| value + offset.component(axis)),
+      );
+    }
+
+    // On the plane, Moore neighbors keep the order of `Direction`
+    if C::DIMENSIONS == 2 && matches!(self.kind, Neighborhood::Moore) {
+      let (dx, dy) = PLANAR_MOORE.get(self.next)?.delta();
+      self.next += 1;
+      return Some(
+        self
+          .center
+          .map_components(|axis, value| value + if axis == 0 { dx } else { dy }),
+      );
+    }
+
+    // Every candidate offset is a base-3 number with one digit per axis, where
+    // the digits 0, 1 and 2 stand for steps of -1, 0 and +1
+    let count = 3usize.pow(C::DIMENSIONS as u32);
+    while self.next < count {
+      let n = self.next;
+      self.next += 1;
+
+      let digit = |axis: usize| (n / 3usize.pow(axis as u32)) % 3;
+      let moved = (0..C::DIMENSIONS).filter(|&axis| digit(axis) != 1).count();
+      let wanted = match self.kind {
+        Neighborhood::VonNeumann => moved == 1,
+        Neighborhood::Moore => moved > 0,
+        Neighborhood::Diagonal => moved == C::DIMENSIONS,
+        Neighborhood::Custom(_) => unreachable!(),
+      };
+
+      if wanted {
+        return Some(
+          self
+            .center
+            .map_components(|axis, value| value + digit(axis) as isize - 1),
+        );
+      }
+    }
+
+    None
   }
+}
 
-  fn toward(&self, direction: Direction) -> TowardIter {
+// Walking in a compass direction only makes sense on the plane. Rotations and
+// reflections treat the coordinate as a vector from the origin, with +y north.
+pub trait Planar: Coordinate {
+  fn toward(&self, direction: Direction) -> TowardIter<Self> {
     TowardIter::new(*self, direction)
   }
+
+  // Positive degrees turn clockwise, negative counter-clockwise
+  fn rotate(&self, degrees: isize) -> Result<Self, String> {
+    if degrees % 90 != 0 {
+      return Err(format!("Cannot rotate a vector by {} degrees", degrees));
+    }
+
+    let (x, y) = (self.component(0), self.component(1));
+    let (x, y) = match (degrees / 90).rem_euclid(4) {
+      0 => (x, y),
+      1 => (y, -x),
+      2 => (-x, -y),
+      _ => (-y, x),
+    };
+    Ok(Self::from_xy(x, y))
+  }
+
+  // Mirrors across the x axis, so north and south swap
+  #[allow(dead_code)]
+  fn reflect_x(&self) -> Self {
+    Self::from_xy(self.component(0), -self.component(1))
+  }
+
+  // Mirrors across the y axis, so east and west swap
+  #[allow(dead_code)]
+  fn reflect_y(&self) -> Self {
+    Self::from_xy(-self.component(0), self.component(1))
+  }
+}
+
+impl Planar for (isize, isize) {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Point {
+  pub x: isize,
+  pub y: isize,
+}
+
+impl Point {
+  pub fn new(x: isize, y: isize) -> Self {
+    Self { x, y }
+  }
+
+  pub fn manhattan(&self, other: Point) -> isize {
+    (self.x - other.x).abs() + (self.y - other.y).abs()
+  }
+
+  #[allow(dead_code)]
+  pub fn chebyshev(&self, other: Point) -> isize {
+    (self.x - other.x).abs().max((self.y - other.y).abs())
+  }
+
+  #[allow(dead_code)]
+  pub fn euclidean_sq(&self, other: Point) -> isize {
+    let (dx, dy) = (self.x - other.x, self.y - other.y);
+    dx * dx + dy * dy
+  }
+}
+
+impl From<(isize, isize)> for Point {
+  fn from((x, y): (isize, isize)) -> Self {
+    Self::new(x, y)
+  }
 }
 
-pub struct TowardIter {
-  last: (isize, isize),
+impl From<Point> for (isize, isize) {
+  fn from(point: Point) -> Self {
+    (point.x, point.y)
+  }
+}
+
+impl From<Direction> for Point {
+  fn from(direction: Direction) -> Self {
+    direction.delta().into()
+  }
+}
+
+impl Add for Point {
+  type Output = Point;
+
+  fn add(self, other: Point) -> Point {
+    Point::new(self.x + other.x, self.y + other.y)
+  }
+}
+
+impl Sub for Point {
+  type Output = Point;
+
+  fn sub(self, other: Point) -> Point {
+    Point::new(self.x - other.x, self.y - other.y)
+  }
+}
+
+impl Mul<isize> for Point {
+  type Output = Point;
+
+  fn mul(self, factor: isize) -> Point {
+    Point::new(self.x * factor, self.y * factor)
+  }
+}
+
+impl Neg for Point {
+  type Output = Point;
+
+  fn neg(self) -> Point {
+    Point::new(-self.x, -self.y)
+  }
+}
+
+impl Coordinate for Point {
+  const DIMENSIONS: usize = 2;
+
+  fn component(&self, axis: usize) -> isize {
+    match axis {
+      0 => self.x,
+      1 => self.y,
+      _ => panic!("No axis {} in a point", axis),
+    }
+  }
+
+  fn map_components<F>(&self, mut f: F) -> Self
+  where
+    F: FnMut(usize, isize) -> isize,
+  {
+    Point::new(f(0, self.x), f(1, self.y))
+  }
+}
+
+impl Planar for Point {}
+
+pub struct TowardIter<C = (isize, isize)> {
+  last: C,
   direction: Direction,
 }
 
-impl TowardIter {
-  fn new(coord: (isize, isize), direction: Direction) -> Self {
+impl<C: Planar> TowardIter<C> {
+  fn new(coord: C, direction: Direction) -> Self {
     Self {
       last: coord,
       direction,
@@ -94,11 +405,11 @@ impl TowardIter {
   }
 }
 
-impl Iterator for TowardIter {
-  type Item = (isize, isize);
+impl<C: Planar> Iterator for TowardIter<C> {
+  type Item = C;
   fn next(&mut self) -> Option<Self::Item> {
     let (dx, dy) = self.direction.delta();
-    self.last = (self.last.0 + dx, self.last.1 + dy);
+    self.last = C::from_xy(self.last.component(0) + dx, self.last.component(1) + dy);
     Some(self.last)
   }
 }
@@ -106,7 +417,7 @@ impl Iterator for TowardIter {
 #[test]
 fn test_coords() {
   let p = (1, 3);
-  let n: Vec<(isize, isize)> = p.neighbors();
+  let n: Vec<(isize, isize)> = p.neighbors().collect();
   assert_eq!(
     n,
     vec![
@@ -133,3 +444,127 @@ fn test_infinite_coords() {
     .collect::<Vec<_>>();
   assert_eq!(ne, vec![(2, 2), (4, 4), (6, 6)])
 }
+
+#[test]
+fn test_n_dimensional_coords() {
+  let p = (1, 2, 3);
+  let n: Vec<_> = p.neighbors().collect();
+  assert_eq!(n.len(), 26);
+  assert_eq!(n[0], (0, 1, 2));
+  assert_eq!(n[25], (2, 3, 4));
+  assert!(!n.contains(&p));
+  assert!(n.contains(&(1, 2, 4)));
+
+  let p = (0, 0, 0, 0);
+  let n: Vec<_> = p.neighbors().collect();
+  assert_eq!(n.len(), 80);
+  assert!(!n.contains(&p));
+  assert!(n
+    .iter()
+    .all(|c| (0..4).all(|axis| c.component(axis).abs() <= 1)));
+
+  assert_eq!(<(isize, isize, isize)>::from_xy(4, -2), (4, -2, 0));
+  assert_eq!(
+    (1, 2, 3, 4).map_components(|axis, v| v * axis as isize),
+    (0, 2, 6, 12)
+  );
+}
+
+#[test]
+fn test_neighborhoods() {
+  let p = (1, 3);
+  let orthogonal: Vec<_> = p.neighbors_with(Neighborhood::VonNeumann).collect();
+  assert_eq!(orthogonal, vec![(1, 2), (0, 3), (2, 3), (1, 4)]);
+
+  let diagonal: Vec<_> = p.neighbors_with(Neighborhood::Diagonal).collect();
+  assert_eq!(diagonal, vec![(0, 2), (2, 2), (0, 4), (2, 4)]);
+
+  let knight_moves = [(1, 2), (-2, -1)];
+  let custom: Vec<_> = p
+    .neighbors_with(Neighborhood::Custom(&knight_moves))
+    .collect();
+  assert_eq!(custom, vec![(2, 5), (-1, 2)]);
+
+  assert_eq!(
+    (0, 0, 0).neighbors_with(Neighborhood::VonNeumann).count(),
+    6
+  );
+  assert_eq!((0, 0, 0).neighbors_with(Neighborhood::Diagonal).count(), 8);
+  assert_eq!(
+    (0, 0, 0, 0)
+      .neighbors_with(Neighborhood::VonNeumann)
+      .count(),
+    8
+  );
+}
+
+#[test]
+fn test_direction_rotation() {
+  assert_eq!(Direction::N.rotate(90), Ok(Direction::E));
+  assert_eq!(Direction::N.rotate(-90), Ok(Direction::W));
+  assert_eq!(Direction::E.rotate(270), Ok(Direction::N));
+  assert_eq!(Direction::SW.rotate(45), Ok(Direction::W));
+  assert_eq!(Direction::NW.rotate(-720), Ok(Direction::NW));
+  assert!(Direction::N.rotate(30).is_err());
+
+  for dir in CLOCKWISE.iter() {
+    assert_eq!(dir.rotate(90).unwrap(), dir.right_90());
+    assert_eq!(dir.rotate(-90).unwrap(), dir.left_90());
+    assert_eq!(dir.opposite().opposite(), *dir);
+    let (dx, dy) = dir.delta();
+    assert_eq!(dir.opposite().delta(), (-dx, -dy));
+  }
+
+  assert_eq!(Direction::from_char('S'), Ok(Direction::S));
+  assert!(Direction::from_char('X').is_err());
+  assert_eq!(
+    Direction::all_cardinal(),
+    [Direction::N, Direction::E, Direction::S, Direction::W]
+  );
+}
+
+#[test]
+fn test_vector_rotation() {
+  let v = (10, 4);
+  assert_eq!(v.rotate(90), Ok((4, -10)));
+  assert_eq!(v.rotate(-90), Ok((-4, 10)));
+  assert_eq!(v.rotate(180), Ok((-10, -4)));
+  assert_eq!(v.rotate(-270), v.rotate(90));
+  assert_eq!(v.rotate(360), Ok(v));
+  assert!(v.rotate(45).is_err());
+
+  assert_eq!(v.reflect_x(), (10, -4));
+  assert_eq!(v.reflect_y(), (-10, 4));
+
+  // Rotating a direction's delta matches rotating the direction itself
+  for dir in Direction::all_cardinal().iter() {
+    assert_eq!(dir.delta().rotate(90), Ok(dir.rotate(90).unwrap().delta()));
+  }
+}
+
+#[test]
+fn test_points() {
+  use super::grid::{Grid, GridStorage};
+
+  let a = Point::new(3, -4);
+  let b: Point = (-1, 2).into();
+  assert_eq!(a + b, Point::new(2, -2));
+  assert_eq!(a - b, Point::new(4, -6));
+  assert_eq!(a * 3, Point::new(9, -12));
+  assert_eq!(-a, Point::new(-3, 4));
+  assert_eq!(<(isize, isize)>::from(a), (3, -4));
+  assert_eq!(Point::from(Direction::NW), Point::new(-1, 1));
+
+  assert_eq!(a.manhattan(b), 10);
+  assert_eq!(a.chebyshev(b), 6);
+  assert_eq!(a.euclidean_sq(b), 52);
+  assert_eq!(a.manhattan(Point::default()), 7);
+
+  assert_eq!(a.rotate(90), Ok(Point::new(-4, -3)));
+  let ray: Vec<_> = a.toward(Direction::E).take(2).collect();
+  assert_eq!(ray, vec![Point::new(4, -4), Point::new(5, -4)]);
+
+  let mut g: Grid<char, Point> = Grid::new();
+  g.insert(a + b, '#');
+  assert_eq!(g.get(Point::new(2, -2)), Some(&'#'));
+}
@@ -1,15 +1,24 @@
 use itertools::Itertools;
+use std::collections::HashMap;
 
-pub fn run(input: String, _args: &[String]) {
-  let nums: Vec<u32> = input
+// With no arguments, solves the puzzle. Otherwise takes a target sum, an
+// optional group size (default 2), and `--count` to only count the groups.
+pub fn run(input: String, args: &[String]) {
+  let nums: Vec<i64> = input
     .lines()
     .map(|line| {
       line
-        .parse::<u32>()
+        .parse::<i64>()
         .unwrap_or_else(|_| panic!("Could not parse line: {}", line))
     })
     .collect();
 
+  if !args.is_empty() {
+    let (sum, count, count_only) = parse_args(args).unwrap_or_else(|err| panic!("{}", err));
+    report_groups(&nums, count, sum, count_only);
+    return;
+  }
+
   let two_elems =
     find_elems_adding_to(&nums, 2, 2020).expect("Couldn't find two items adding to 2020");
   if let [a, b] = two_elems[..] {
@@ -23,15 +32,195 @@ pub fn run(input: String, _args: &[String]) {
   }
 }
 
-fn find_elems_adding_to(v: &[u32], count: usize, sum: u32) -> Option<Vec<u32>> {
-  let combinations = v.iter().cloned().combinations(count);
-  for combo in combinations {
-    if combo.iter().sum::<u32>() == sum {
-      return Some(combo);
+fn parse_args(args: &[String]) -> Result<(i64, usize, bool), String> {
+  let count_only = args.iter().any(|arg| arg == "--count");
+  let positional: Vec<&String> = args.iter().filter(|&arg| arg != "--count").collect();
+
+  let sum = match positional.first() {
+    Some(arg) => arg
+      .parse()
+      .map_err(|_| format!("Could not parse target sum: {}", arg))?,
+    None => 2020,
+  };
+  let count = match positional.get(1) {
+    Some(arg) => arg
+      .parse()
+      .map_err(|_| format!("Could not parse group size: {}", arg))?,
+    None => 2,
+  };
+  if positional.len() > 2 {
+    return Err(format!("Unexpected argument: {}", positional[2]));
+  }
+
+  Ok((sum, count, count_only))
+}
+
+fn report_groups(nums: &[i64], count: usize, sum: i64, count_only: bool) {
+  if count_only {
+    let total = count_groups_adding_to(nums, count, sum);
+    println!("{} groups of {} entries add to {}", total, count, sum);
+    return;
+  }
+
+  let groups = find_groups_adding_to(nums, count, sum);
+  for group in groups.iter() {
+    let values: Vec<String> = group.iter().map(|&i| nums[i].to_string()).collect();
+    let product: i64 = group.iter().map(|&i| nums[i]).product();
+    println!(
+      "Lines {:?}: {} multiply to give {}",
+      group.iter().map(|i| i + 1).collect::<Vec<_>>(),
+      values.join(", "),
+      product
+    );
+  }
+  println!(
+    "{} groups of {} entries add to {}",
+    groups.len(),
+    count,
+    sum
+  );
+}
+
+// Groups are sets of `count` different indices into `v`, in ascending order.
+// Entries with equal values are still different entries, so each can appear
+// in its own group.
+fn find_elems_adding_to(v: &[i64], count: usize, sum: i64) -> Option<Vec<i64>> {
+  let mut first = None;
+  solve(v, count, sum, &mut |group| {
+    first = Some(group.iter().map(|&i| v[i]).collect());
+    false
+  });
+
+  first
+}
+
+fn find_groups_adding_to(v: &[i64], count: usize, sum: i64) -> Vec<Vec<usize>> {
+  let mut groups = vec![];
+  solve(v, count, sum, &mut |group| {
+    groups.push(group.to_vec());
+    true
+  });
+  groups.sort();
+
+  groups
+}
+
+fn count_groups_adding_to(v: &[i64], count: usize, sum: i64) -> usize {
+  let mut total = 0;
+  solve(v, count, sum, &mut |_| {
+    total += 1;
+    true
+  });
+
+  total
+}
+
+// Calls `visit` with each group found, stopping early if it returns false
+type Visitor<'a> = dyn FnMut(&[usize]) -> bool + 'a;
+
+fn solve(v: &[i64], count: usize, sum: i64, visit: &mut Visitor) {
+  match count {
+    2 => two_sum(v, sum, visit),
+    3 => three_sum(v, sum, visit),
+    _ => k_sum(v, count, sum, visit),
+  }
+}
+
+// O(n): look each value's partners up among the entries already seen
+fn two_sum(v: &[i64], sum: i64, visit: &mut Visitor) {
+  let mut seen: HashMap<i64, Vec<usize>> = HashMap::new();
+
+  for (j, &value) in v.iter().enumerate() {
+    for &i in seen.get(&(sum - value)).into_iter().flatten() {
+      if !visit(&[i, j]) {
+        return;
+      }
     }
+    seen.entry(value).or_default().push(j);
   }
+}
 
-  None
+// O(n^2): for each smallest entry, close in on the other two from both ends of
+// the entries sorted by value
+fn three_sum(v: &[i64], sum: i64, visit: &mut Visitor) {
+  let mut order: Vec<usize> = (0..v.len()).collect();
+  order.sort_by_key(|&i| v[i]);
+  let value = |pos: usize| v[order[pos]];
+  let mut emit = |a: usize, b: usize, c: usize| {
+    let mut group = [order[a], order[b], order[c]];
+    group.sort_unstable();
+    visit(&group)
+  };
+
+  for a in 0..order.len() {
+    let (mut lo, mut hi) = (a + 1, order.len().saturating_sub(1));
+    while lo < hi {
+      let total = value(a) + value(lo) + value(hi);
+      if total < sum {
+        lo += 1;
+      } else if total > sum {
+        hi -= 1;
+      } else if value(lo) == value(hi) {
+        // Every pair from here on matches
+        for (b, c) in (lo..=hi).tuple_combinations() {
+          if !emit(a, b, c) {
+            return;
+          }
+        }
+        break;
+      } else {
+        let lo_end = (lo..hi)
+          .take_while(|&b| value(b) == value(lo))
+          .last()
+          .unwrap();
+        let hi_start = (lo..=hi)
+          .rev()
+          .take_while(|&c| value(c) == value(hi))
+          .last()
+          .unwrap();
+        for (b, c) in (lo..=lo_end).cartesian_product(hi_start..=hi) {
+          if !emit(a, b, c) {
+            return;
+          }
+        }
+        lo = lo_end + 1;
+        hi = hi_start - 1;
+      }
+    }
+  }
+}
+
+// O(n^ceil(k/2)): every group splits into its first k/2 indices and the rest,
+// so index the sums of the first halves and look each second half's
+// complement up, only pairing halves that don't overlap
+fn k_sum(v: &[i64], count: usize, sum: i64, visit: &mut Visitor) {
+  if count > v.len() {
+    return;
+  }
+
+  let half = count / 2;
+  let mut first_halves: HashMap<i64, Vec<Vec<usize>>> = HashMap::new();
+  for indices in (0..v.len()).combinations(half) {
+    let total = indices.iter().map(|&i| v[i]).sum();
+    first_halves.entry(total).or_default().push(indices);
+  }
+
+  for rest in (0..v.len()).combinations(count - half) {
+    let rest_total: i64 = rest.iter().map(|&i| v[i]).sum();
+    let start = rest.first().copied().unwrap_or(v.len());
+    let matches = first_halves.get(&(sum - rest_total));
+
+    for first in matches.into_iter().flatten() {
+      if first.last().is_some_and(|&i| i >= start) {
+        continue;
+      }
+
+      let group: Vec<usize> = first.iter().chain(rest.iter()).copied().collect();
+      if !visit(&group) {
+        return;
+      }
+    }
+  }
 }
 
 #[test]
@@ -39,4 +228,53 @@ fn test_find_array_adds_to() {
   let v = vec![1721, 979, 366, 299, 675, 1456];
   assert_eq!(find_elems_adding_to(&v, 2, 2020), Some(vec![1721, 299]));
   assert_eq!(find_elems_adding_to(&v, 3, 2020), Some(vec![979, 366, 675]));
+  assert_eq!(find_groups_adding_to(&v, 2, 2020), vec![vec![0, 3]]);
+}
+
+#[test]
+fn test_k_sums_match_brute_force() {
+  let v = vec![5, -3, 2, 2, 7, 0, -1, 4, 2, -6, 3, -3];
+  let brute_force = |count: usize, sum: i64| {
+    (0..v.len())
+      .combinations(count)
+      .filter(|group| group.iter().map(|&i| v[i]).sum::<i64>() == sum)
+      .collect::<Vec<_>>()
+  };
+
+  for count in 0..=6 {
+    for sum in -8..=20 {
+      let groups = find_groups_adding_to(&v, count, sum);
+      assert_eq!(
+        groups,
+        brute_force(count, sum),
+        "{} adding to {}",
+        count,
+        sum
+      );
+      assert_eq!(count_groups_adding_to(&v, count, sum), groups.len());
+      let first = find_elems_adding_to(&v, count, sum);
+      assert_eq!(first.is_some(), !groups.is_empty());
+    }
+  }
+}
+
+#[test]
+fn test_duplicates_and_refunds() {
+  // Each pair of the three equal entries is its own group
+  let v = vec![2, 2, 2, -5, 10];
+  assert_eq!(
+    find_groups_adding_to(&v, 2, 4),
+    vec![vec![0, 1], vec![0, 2], vec![1, 2]]
+  );
+  assert_eq!(count_groups_adding_to(&v, 3, 6), 1);
+  assert_eq!(find_groups_adding_to(&v, 3, 6), vec![vec![0, 1, 2]]);
+  assert_eq!(find_elems_adding_to(&v, 2, 4), Some(vec![2, 2]));
+  assert_eq!(find_elems_adding_to(&v, 2, -3), Some(vec![2, -5]));
+  assert_eq!(find_elems_adding_to(&v, 2, 6), None);
+
+  let args = |s: &str| s.split(' ').map(|arg| arg.to_string()).collect::<Vec<_>>();
+  assert_eq!(parse_args(&args("100 4 --count")), Ok((100, 4, true)));
+  assert_eq!(parse_args(&args("-7")), Ok((-7, 2, false)));
+  assert!(parse_args(&args("ten")).is_err());
+  assert!(parse_args(&args("10 2 3")).is_err());
 }
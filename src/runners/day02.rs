@@ -1,61 +1,454 @@
 use regex::Regex;
-use std::fmt::Display;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
 use std::str::FromStr;
+use std::thread;
+use unicode_segmentation::UnicodeSegmentation;
 
-pub fn run(input: String, _args: &[String]) {
-  let policies_and_passwords = input
-    .lines()
-    .map(|s| {
-      let mut split = s.split(": ");
-      let left = split.next().unwrap();
-      let right = split.next().unwrap();
-      let policy = left.parse::<PasswordPolicy>().unwrap();
-      (policy, right)
-    })
-    .collect::<Vec<_>>();
+// With no arguments, counts the passwords passing each of the puzzle's two
+// policies. Given the path of a rules file (see `parse_rules`), reports which
+// of those rules each password fails instead. Lines that can't be parsed are
+// reported and skipped.
+//
+// `--stream <path> [workers]` counts the puzzle policies over a file of any
+// size instead, reading it in chunks spread over `workers` threads.
+pub fn run(input: String, args: &[String]) {
+  if args.first().map(String::as_str) == Some("--stream") {
+    stream(&args[1..]);
+    return;
+  }
 
-  let num_passing_old = policies_and_passwords
-    .iter()
-    .filter(|(policy, pass)| policy.check_old(pass))
-    .count();
+  let mut policies_and_passwords = vec![];
+  for (line_num, line) in input.lines().enumerate() {
+    match parse_line(line) {
+      Ok(entry) => {
+        if let Some(warning) = entry.0.old_policy_warning() {
+          eprintln!("Line {}: {}", line_num + 1, warning);
+        }
+        policies_and_passwords.push(entry);
+      }
+      Err(err) => eprintln!("Skipping line {}: {}", line_num + 1, err),
+    }
+  }
 
-  let num_passing_new = policies_and_passwords
-    .iter()
-    .filter(|(policy, pass)| policy.check_new(pass))
-    .count();
+  if let Some(path) = args.first() {
+    let config =
+      fs::read_to_string(path).unwrap_or_else(|err| panic!("Could not read {}: {}", path, err));
+    let rules = parse_rules(&config).unwrap();
+    report(&rules, &policies_and_passwords);
+    return;
+  }
+
+  let mut tally = Tally::default();
+  for (policy, pass) in policies_and_passwords.iter() {
+    tally.add(policy, pass);
+  }
+  tally.print();
+}
+
+fn stream(args: &[String]) {
+  let path = args.first().expect("Usage: --stream <path> [workers]");
+  let workers = match args.get(1) {
+    Some(arg) => arg
+      .parse()
+      .unwrap_or_else(|_| panic!("Could not parse worker count: {}", arg)),
+    None => 1,
+  };
+
+  let file = File::open(path).unwrap_or_else(|err| panic!("Could not open {}: {}", path, err));
+  let tally = tally_stream(BufReader::new(file), STREAM_CHUNK_LINES, workers)
+    .unwrap_or_else(|err| panic!("Could not read {}: {}", path, err));
+  tally.print();
+  if tally.invalid > 0 {
+    println!("{} lines could not be parsed", tally.invalid);
+  }
+}
+
+const STREAM_CHUNK_LINES: usize = 10_000;
+
+// Both policies are checked in the same pass over each password
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Tally {
+  passing_old: usize,
+  passing_new: usize,
+  invalid: usize,
+}
+
+impl Tally {
+  fn add(&mut self, policy: &PasswordPolicy, pass: &str) {
+    self.passing_old += policy.check_old(pass) as usize;
+    self.passing_new += policy.check_new(pass) as usize;
+  }
+
+  // Lines that aren't valid UTF-8 count as invalid too
+  fn add_line(&mut self, line: &[u8]) {
+    match std::str::from_utf8(line).map(parse_line) {
+      Ok(Ok((policy, pass))) => self.add(&policy, pass),
+      _ => self.invalid += 1,
+    }
+  }
+
+  fn merge(&mut self, other: Tally) {
+    self.passing_old += other.passing_old;
+    self.passing_new += other.passing_new;
+    self.invalid += other.invalid;
+  }
+
+  fn print(&self) {
+    println!(
+      "{} passwords match based on the old policy",
+      self.passing_old
+    );
+    println!(
+      "{} passwords match based on the new policy",
+      self.passing_new
+    );
+  }
+}
+
+// Reads up to `workers` chunks of `chunk_lines` lines at a time and tallies
+// them in parallel, so memory use doesn't grow with the size of the input
+fn tally_stream<R: BufRead>(
+  mut reader: R,
+  chunk_lines: usize,
+  workers: usize,
+) -> io::Result<Tally> {
+  let mut total = Tally::default();
+
+  loop {
+    let mut chunks = vec![];
+    for _ in 0..workers.max(1) {
+      let chunk = read_chunk(&mut reader, chunk_lines)?;
+      if chunk.is_empty() {
+        break;
+      }
+      chunks.push(chunk);
+    }
+
+    if chunks.is_empty() {
+      return Ok(total);
+    }
+
+    let tally_chunk = |chunk: &Vec<Vec<u8>>| {
+      let mut tally = Tally::default();
+      for line in chunk.iter() {
+        tally.add_line(line);
+      }
+      tally
+    };
+    let tallies: Vec<Tally> = if chunks.len() == 1 {
+      vec![tally_chunk(&chunks[0])]
+    } else {
+      thread::scope(|scope| {
+        let handles: Vec<_> = chunks
+          .iter()
+          .map(|chunk| scope.spawn(move || tally_chunk(chunk)))
+          .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+      })
+    };
+
+    for tally in tallies {
+      total.merge(tally);
+    }
+  }
+}
+
+// Up to `count` raw lines, without their line endings. They're left undecoded
+// so one bad line doesn't stop the rest of the stream from being read.
+fn read_chunk<R: BufRead>(reader: &mut R, count: usize) -> io::Result<Vec<Vec<u8>>> {
+  let mut lines = vec![];
+  while lines.len() < count {
+    let mut line = vec![];
+    if reader.read_until(b'\n', &mut line)? == 0 {
+      break;
+    }
+    if line.ends_with(b"\n") {
+      line.pop();
+      if line.ends_with(b"\r") {
+        line.pop();
+      }
+    }
+    lines.push(line);
+  }
+  Ok(lines)
+}
+
+fn parse_line(line: &str) -> Result<(PasswordPolicy, &str), String> {
+  let mut split = line.splitn(2, ": ");
+  let left = split.next().unwrap();
+  let right = split
+    .next()
+    .ok_or_else(|| format!("No password after policy: {}", line))?;
+
+  Ok((left.parse()?, right))
+}
+
+fn report(rules: &[Box<dyn PolicyRule>], policies_and_passwords: &[(PasswordPolicy, &str)]) {
+  let mut num_passing = 0;
+  for (policy, pass) in policies_and_passwords.iter() {
+    let failed = failed_rules(rules, policy, pass);
+    if failed.is_empty() {
+      num_passing += 1;
+    } else {
+      println!("{}: {} fails {}", policy, pass, failed.join(", "));
+    }
+  }
 
   println!(
-    "{} passwords match based on the old policy",
-    num_passing_old
-  );
-  println!(
-    "{} passwords match based on the new policy",
-    num_passing_new
+    "{} of {} passwords pass every rule",
+    num_passing,
+    policies_and_passwords.len()
   );
 }
 
+fn failed_rules(rules: &[Box<dyn PolicyRule>], policy: &PasswordPolicy, s: &str) -> Vec<String> {
+  rules
+    .iter()
+    .filter(|rule| !rule.check(policy, s))
+    .map(|rule| rule.to_string())
+    .collect()
+}
+
+// A rule a password can be checked against. The policy from the password's
+// own line is passed along, though most rules ignore it.
+trait PolicyRule: Display {
+  fn check(&self, policy: &PasswordPolicy, s: &str) -> bool;
+}
+
+// One rule per line, with blank lines and lines starting with `#` ignored:
+//
+//   old                  the letter count must be within the line's policy
+//   new                  exactly one of the policy's positions holds the letter
+//   class <class> <min>  at least `min` lower, upper, digit or symbol characters
+//   forbid <text>        must not contain `text`
+//   entropy <bits>       at least `bits` of Shannon entropy in total
+//   regex <pattern>      must match `pattern`
+fn parse_rules(s: &str) -> Result<Vec<Box<dyn PolicyRule>>, String> {
+  s.lines()
+    .map(|line| line.trim())
+    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+    .map(|line| {
+      let (kind, arg) = match line.find(' ') {
+        Some(idx) => (&line[..idx], line[idx + 1..].trim()),
+        None => (line, ""),
+      };
+
+      let rule: Box<dyn PolicyRule> = match kind {
+        "old" => Box::new(OldRule),
+        "new" => Box::new(NewRule),
+        "class" => Box::new(arg.parse::<ClassRule>()?),
+        "forbid" if !arg.is_empty() => Box::new(ForbidRule(arg.to_string())),
+        "entropy" => {
+          Box::new(EntropyRule(arg.parse().map_err(|_| {
+            format!("Could not parse entropy threshold: {}", arg)
+          })?))
+        }
+        "regex" => Box::new(RegexRule(
+          Regex::new(arg).map_err(|err| format!("Bad regex {}: {}", arg, err))?,
+        )),
+        _ => return Err(format!("Could not parse rule: {}", line)),
+      };
+      Ok(rule)
+    })
+    .collect()
+}
+
+struct OldRule;
+
+impl PolicyRule for OldRule {
+  fn check(&self, policy: &PasswordPolicy, s: &str) -> bool {
+    policy.check_old(s)
+  }
+}
+
+impl Display for OldRule {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "old policy")
+  }
+}
+
+struct NewRule;
+
+impl PolicyRule for NewRule {
+  fn check(&self, policy: &PasswordPolicy, s: &str) -> bool {
+    policy.check_new(s)
+  }
+}
+
+impl Display for NewRule {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "new policy")
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+  Lower,
+  Upper,
+  Digit,
+  Symbol,
+}
+
+impl CharClass {
+  fn contains(self, c: char) -> bool {
+    match self {
+      CharClass::Lower => c.is_lowercase(),
+      CharClass::Upper => c.is_uppercase(),
+      CharClass::Digit => c.is_ascii_digit(),
+      CharClass::Symbol => !c.is_alphanumeric() && !c.is_whitespace(),
+    }
+  }
+}
+
+struct ClassRule {
+  class: CharClass,
+  min: usize,
+}
+
+impl PolicyRule for ClassRule {
+  fn check(&self, _policy: &PasswordPolicy, s: &str) -> bool {
+    s.chars().filter(|&c| self.class.contains(c)).count() >= self.min
+  }
+}
+
+impl FromStr for ClassRule {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut parts = s.split_whitespace();
+    let class = match parts.next() {
+      Some("lower") => CharClass::Lower,
+      Some("upper") => CharClass::Upper,
+      Some("digit") => CharClass::Digit,
+      Some("symbol") => CharClass::Symbol,
+      _ => return Err(format!("Could not parse character class: {}", s)),
+    };
+    let min = match parts.next() {
+      Some(min) => min
+        .parse()
+        .map_err(|_| format!("Could not parse character count: {}", s))?,
+      None => 1,
+    };
+
+    Ok(Self { class, min })
+  }
+}
+
+impl Display for ClassRule {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let class = format!("{:?}", self.class).to_lowercase();
+    write!(f, "at least {} {}", self.min, class)
+  }
+}
+
+struct ForbidRule(String);
+
+impl PolicyRule for ForbidRule {
+  fn check(&self, _policy: &PasswordPolicy, s: &str) -> bool {
+    !s.contains(&self.0)
+  }
+}
+
+impl Display for ForbidRule {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "no \"{}\"", self.0)
+  }
+}
+
+struct EntropyRule(f64);
+
+impl PolicyRule for EntropyRule {
+  fn check(&self, _policy: &PasswordPolicy, s: &str) -> bool {
+    entropy(s) >= self.0
+  }
+}
+
+impl Display for EntropyRule {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "entropy of {} bits", self.0)
+  }
+}
+
+// Shannon entropy of the password's own character frequencies, in bits per
+// character, times its length
+fn entropy(s: &str) -> f64 {
+  let mut counts: HashMap<char, usize> = HashMap::new();
+  for c in s.chars() {
+    *counts.entry(c).or_insert(0) += 1;
+  }
+
+  let len = s.chars().count() as f64;
+  let per_char: f64 = counts
+    .values()
+    .map(|&count| {
+      let p = count as f64 / len;
+      -p * p.log2()
+    })
+    .sum();
+
+  per_char * len
+}
+
+struct RegexRule(Regex);
+
+impl PolicyRule for RegexRule {
+  fn check(&self, _policy: &PasswordPolicy, s: &str) -> bool {
+    self.0.is_match(s)
+  }
+}
+
+impl Display for RegexRule {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "matching /{}/", self.0)
+  }
+}
+
+// The letter and passwords are compared by user-perceived character (grapheme
+// cluster), so an accented letter made of several code points still counts
+// as one character.
 struct PasswordPolicy {
   min: usize,
   max: usize,
-  letter: char,
+  letter: String,
 }
 
 impl PasswordPolicy {
-  fn new(min: usize, max: usize, letter: char) -> Self {
-    Self { min, max, letter }
+  fn new(min: usize, max: usize, letter: &str) -> Self {
+    Self {
+      min,
+      max,
+      letter: letter.to_string(),
+    }
   }
 
   pub fn check_old(&self, s: &str) -> bool {
-    let count = s.chars().filter(|&c| c == self.letter).count();
+    let count = s.graphemes(true).filter(|&g| g == self.letter).count();
     count >= self.min && count <= self.max
   }
 
+  // A count range that runs backwards, like 5-2, can't be met. The new policy
+  // still reads the same numbers as two valid positions.
+  fn old_policy_warning(&self) -> Option<String> {
+    match self.min > self.max {
+      true => Some(format!("{} can never pass the old policy", self)),
+      false => None,
+    }
+  }
+
+  // Positions past the end of the password never hold the letter
   pub fn check_new(&self, s: &str) -> bool {
-    let chars = s.chars().collect::<Vec<_>>();
-    let left_char = chars[self.min - 1];
-    let right_char = chars[self.max - 1];
+    let graphemes = s.graphemes(true).collect::<Vec<_>>();
+    let holds_letter = |pos: usize| {
+      pos
+        .checked_sub(1)
+        .and_then(|idx| graphemes.get(idx))
+        .is_some_and(|&g| g == self.letter)
+    };
 
-    (left_char == self.letter || right_char == self.letter) && left_char != right_char
+    holds_letter(self.min) != holds_letter(self.max)
   }
 }
 
@@ -64,18 +457,28 @@ impl FromStr for PasswordPolicy {
 
   fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
     lazy_static! {
-      static ref PASS_POLICY_RE: Regex = Regex::new(r"^(\d+)-(\d+) ([a-z])").unwrap();
+      static ref PASS_POLICY_RE: Regex = Regex::new(r"^(\d+)-(\d+) (\S+)$").unwrap();
     }
 
-    match PASS_POLICY_RE.captures(s) {
-      Some(cap) => {
-        let low = cap.get(1).unwrap().as_str().parse::<usize>().unwrap();
-        let high = cap.get(2).unwrap().as_str().parse::<usize>().unwrap();
-        let letter = cap.get(3).unwrap().as_str().parse::<char>().unwrap();
-        Ok(PasswordPolicy::new(low, high, letter))
-      }
-      _ => Err(format!("Unable to parse password policy: {}", s)),
+    let cap = PASS_POLICY_RE
+      .captures(s)
+      .ok_or_else(|| format!("Unable to parse password policy: {}", s))?;
+    let parse_position = |idx: usize| match cap.get(idx).unwrap().as_str().parse::<usize>() {
+      Ok(0) => Err(format!("Positions start at 1 in password policy: {}", s)),
+      Ok(pos) => Ok(pos),
+      Err(_) => Err(format!("Position too large in password policy: {}", s)),
+    };
+    let low = parse_position(1)?;
+    let high = parse_position(2)?;
+    let letter = cap.get(3).unwrap().as_str();
+    if letter.graphemes(true).count() != 1 {
+      return Err(format!(
+        "Expected a single letter in password policy: {}",
+        s
+      ));
     }
+
+    Ok(PasswordPolicy::new(low, high, letter))
   }
 }
 
@@ -108,3 +511,107 @@ fn test_new_password_policy() {
   assert_eq!(policy.check_new("acbec"), false);
   assert_eq!(policy.check_new("abcde"), false);
 }
+
+#[test]
+fn test_policy_rules() {
+  let config = super::common::get_input("02_rules_sample").unwrap();
+  let rules = parse_rules(&config).unwrap();
+  assert_eq!(rules.len(), 6);
+
+  let policy: PasswordPolicy = "1-3 a".parse().unwrap();
+  assert_eq!(failed_rules(&rules, &policy, "abcd3"), Vec::<String>::new());
+  assert_eq!(
+    failed_rules(&rules, &policy, "aaaa"),
+    vec![
+      "old policy",
+      "new policy",
+      "at least 1 digit",
+      "entropy of 4 bits",
+    ]
+  );
+  assert_eq!(
+    failed_rules(&rules, &policy, "abcabc9"),
+    vec!["no \"abc9\""]
+  );
+  assert_eq!(
+    failed_rules(&rules, &policy, "Abc5e"),
+    vec!["old policy", "new policy", "matching /^[a-z0-9]+$/"]
+  );
+
+  assert_eq!(entropy("aaaa"), 0.0);
+  assert_eq!(entropy("abcd"), 8.0);
+  let symbols: ClassRule = "symbol 2".parse().unwrap();
+  assert!(symbols.check(&policy, "a!b?"));
+  assert!(!symbols.check(&policy, "a!b c"));
+
+  assert!(parse_rules("class vowel 2").is_err());
+  assert!(parse_rules("regex (").is_err());
+  assert!(parse_rules("entropy lots").is_err());
+  assert!(parse_rules("forbid").is_err());
+}
+
+#[test]
+fn test_unusual_policies() {
+  // A decomposed "é" is two code points but one character
+  let accented = "e\u{301}";
+  let policy: PasswordPolicy = "2-3 x".parse().unwrap();
+  assert!(policy.check_new(&format!("{}xy", accented)));
+  let policy: PasswordPolicy = format!("1-1 {}", accented).parse().unwrap();
+  assert!(policy.check_old(&format!("ab{}c", accented)));
+  assert!(!policy.check_old("abec"));
+  let policy: PasswordPolicy = format!("1-4 {}", accented).parse().unwrap();
+  assert!(policy.check_new(&format!("{}bcd", accented)));
+
+  // Positions past the end just don't hold the letter
+  let policy: PasswordPolicy = "2-9 a".parse().unwrap();
+  assert!(policy.check_new("ba"));
+  assert!(!policy.check_new("b"));
+  assert!(!policy.check_new(""));
+
+  assert!(parse_line("1-3 a: abc").is_ok());
+  assert!(parse_line("0-3 a: abc").is_err());
+  assert!(parse_line("1-0 a: abc").is_err());
+  let (backwards, pass) = parse_line("5-2 a: xaxxx").unwrap();
+  assert!(backwards.check_new(pass));
+  assert!(!backwards.check_old("aaa"));
+  assert!(backwards.old_policy_warning().is_some());
+  let (policy, _) = parse_line("2-2 a: abc").unwrap();
+  assert_eq!(policy.old_policy_warning(), None);
+  assert!(parse_line("1-3 ab: abc").is_err());
+  assert!(parse_line("1-99999999999999999999 a: abc").is_err());
+  assert!(parse_line("1-3 a abc").is_err());
+  assert!(parse_line("").is_err());
+}
+
+#[test]
+fn test_streaming_tally() {
+  let input = super::common::get_input("02").unwrap();
+  let expected = Tally {
+    passing_old: 580,
+    passing_new: 611,
+    invalid: 0,
+  };
+  assert_eq!(tally_stream(input.as_bytes(), 10_000, 1).unwrap(), expected);
+  assert_eq!(tally_stream(input.as_bytes(), 7, 3).unwrap(), expected);
+
+  let lines = "1-3 a: abcde\nnot a policy\n1-3 b: cdefg\n\n2-9 c: ccccccccc\n";
+  let tally = tally_stream(lines.as_bytes(), 2, 2).unwrap();
+  assert_eq!(
+    tally,
+    Tally {
+      passing_old: 2,
+      passing_new: 1,
+      invalid: 2,
+    }
+  );
+  assert_eq!(tally_stream("".as_bytes(), 2, 2).unwrap(), Tally::default());
+  let bytes = &b"1-3 a: a\xff\n1-3 a: abc\r\n\xff\n2-2 b: ab"[..];
+  assert_eq!(
+    tally_stream(bytes, 2, 2).unwrap(),
+    Tally {
+      passing_old: 1,
+      passing_new: 1,
+      invalid: 2,
+    }
+  );
+}
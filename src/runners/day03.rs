@@ -1,56 +1,269 @@
+use super::grid::{Boundary, DenseGrid, GridStorage, RowOrder};
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::iter;
 use std::str::FromStr;
 
-pub fn run(input: String, _args: &[String]) {
+type Coord = (isize, isize);
+
+// Slopes can be given as arguments, like `3,1 1,2` or `-1/2` (see `Slope`),
+// optionally with `--from x,y` to start somewhere other than the top left and
+// `--path` to list every position visited or `--render` to draw it over the
+// map. Options without any slopes use (3, 1). `--search <bound>` instead ranks
+// every slope moving at most `bound` across and down.
+pub fn run(input: String, args: &[String]) {
   let grid: InfiniteHorizontalGrid = input.parse().expect("Couldn't parse grid");
-  let trees_hit = grid.taken_on_slope(3, 1);
-  println!("Moving by (3, 2) you'll hit {} trees", trees_hit);
-
-  let alt1 = grid.taken_on_slope(1, 1);
-  let alt2 = grid.taken_on_slope(5, 1);
-  let alt3 = grid.taken_on_slope(7, 1);
-  let alt4 = grid.taken_on_slope(1, 2);
-  println!(
-    "The other slopes multiplied together is {}",
-    alt1 * alt2 * alt3 * alt4 * trees_hit
-  );
+
+  match args.first().map(String::as_str) {
+    None => {
+      let trees_hit = grid.taken_on_slope(3, 1);
+      println!("Moving by (3, 1) you'll hit {} trees", trees_hit);
+
+      let alt1 = grid.taken_on_slope(1, 1);
+      let alt2 = grid.taken_on_slope(5, 1);
+      let alt3 = grid.taken_on_slope(7, 1);
+      let alt4 = grid.taken_on_slope(1, 2);
+      println!(
+        "The other slopes multiplied together is {}",
+        alt1 * alt2 * alt3 * alt4 * trees_hit
+      );
+    }
+    Some("--search") => {
+      let bound = args
+        .get(1)
+        .and_then(|arg| arg.parse().ok())
+        .expect("Usage: --search <bound>");
+      print_ranking(&grid.rank_slopes(bound));
+    }
+    Some(_) => {
+      let options = parse_args(args).unwrap_or_else(|err| panic!("{}", err));
+
+      let mut product: u64 = 1;
+      for slope in options.slopes.iter() {
+        let path = grid.path_on_slope(options.start, *slope);
+        if options.show_path {
+          for ((x, y), tree) in path.iter() {
+            println!("({}, {}) {}", x, y, if *tree { "tree" } else { "open" });
+          }
+        }
+        if options.render {
+          print!("{}", grid.render_path(&path));
+        }
+
+        let trees_hit = path.iter().filter(|(_, tree)| *tree).count() as u64;
+        println!("Moving by {} you'll hit {} trees", slope, trees_hit);
+        product *= trees_hit;
+      }
+      println!("Those slopes multiplied together is {}", product);
+    }
+  }
 }
 
-struct InfiniteHorizontalGrid {
-  width: usize,
-  height: usize,
-  lines: Vec<Vec<bool>>,
+struct Options {
+  slopes: Vec<Slope>,
+  start: Coord,
+  show_path: bool,
+  render: bool,
 }
 
-impl InfiniteHorizontalGrid {
-  pub fn value_at(&self, row: usize, col: usize) -> Option<bool> {
-    if row >= self.height {
-      return None;
+fn parse_args(args: &[String]) -> Result<Options, String> {
+  let mut options = Options {
+    slopes: vec![],
+    start: (0, 0),
+    show_path: false,
+    render: false,
+  };
+
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--path" => options.show_path = true,
+      "--render" => options.render = true,
+      "--from" => {
+        let start = args.next().ok_or("Expected a position after --from")?;
+        let coords: Vec<isize> = start
+          .split(',')
+          .map(|part| part.trim().parse())
+          .collect::<Result<_, _>>()
+          .map_err(|_| format!("Could not parse start position: {}", start))?;
+        match coords[..] {
+          [x, y] => options.start = (x, y),
+          _ => return Err(format!("Could not parse start position: {}", start)),
+        }
+      }
+      _ => options.slopes.push(arg.parse()?),
     }
+  }
+  if options.slopes.is_empty() {
+    options.slopes.push(Slope { dx: 3, dy: 1 });
+  }
 
-    let mut wrapped_col = col;
+  Ok(options)
+}
+
+fn print_ranking(ranked: &[(Slope, u64)]) {
+  println!("{:>4}  {:<10}  {:>5}", "Rank", "Slope", "Trees");
+  for (rank, (slope, trees)) in ranked.iter().enumerate() {
+    println!("{:>4}  {:<10}  {:>5}", rank + 1, slope.to_string(), trees);
+  }
 
-    while wrapped_col >= self.width {
-      wrapped_col -= self.width;
+  if let (Some((_, fewest)), Some((_, most))) = (ranked.first(), ranked.last()) {
+    let with = |count: u64| {
+      let slopes: Vec<String> = ranked
+        .iter()
+        .filter(|&&(_, trees)| trees == count)
+        .map(|(slope, _)| slope.to_string())
+        .collect();
+      slopes.join(", ")
+    };
+    println!("Fewest trees ({}): {}", fewest, with(*fewest));
+    println!("Most trees ({}): {}", most, with(*most));
+  }
+}
+
+// How far to move right (or left, if negative) and down on each step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Slope {
+  dx: i64,
+  dy: i64,
+}
+
+impl Slope {
+  // A slope of `num / den` columns per row. Moving exactly along it would
+  // mostly land between cells, so this steps from one lattice point on the
+  // line to the next instead: 1/2 moves one right every two rows.
+  fn rational(num: i64, den: i64) -> Result<Self, String> {
+    if den == 0 {
+      return Err("Slope must move down".to_string());
     }
 
-    Some(self.lines[row][wrapped_col])
+    let divisor = gcd(num, den) * den.signum();
+    Ok(Slope {
+      dx: num / divisor,
+      dy: den / divisor,
+    })
+  }
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+  match b {
+    0 => a.abs(),
+    _ => gcd(b, a % b),
+  }
+}
+
+// Either a step, `dx,dy`, or a number of columns per row, as a fraction like
+// `-3/2` or a decimal like `0.25`
+impl FromStr for Slope {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let bad_slope = || format!("Could not parse slope: {}", s);
+    let parse = |part: &str| part.trim().parse::<i64>().map_err(|_| bad_slope());
+
+    if s.contains(',') {
+      let parts = s.split(',').map(parse).collect::<Result<Vec<_>, _>>()?;
+      return match parts[..] {
+        [dx, dy] if dy > 0 => Ok(Slope { dx, dy }),
+        [_, _] => Err(format!("Slope must move down: {}", s)),
+        _ => Err(bad_slope()),
+      };
+    }
+
+    if let Some(idx) = s.find('/') {
+      return Slope::rational(parse(&s[..idx])?, parse(&s[idx + 1..])?);
+    }
+
+    let s = s.trim();
+    let (whole, fraction) = match s.find('.') {
+      Some(idx) => (&s[..idx], &s[idx + 1..]),
+      None => (s, ""),
+    };
+    if fraction.starts_with(['-', '+']) {
+      return Err(bad_slope());
+    }
+    let den = 10_i64
+      .checked_pow(fraction.len() as u32)
+      .ok_or_else(bad_slope)?;
+    let num = format!("{}{}", whole, fraction)
+      .parse::<i64>()
+      .map_err(|_| bad_slope())?;
+
+    Slope::rational(num, den)
+  }
+}
+
+impl Display for Slope {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "({}, {})", self.dx, self.dy)
+  }
+}
+
+struct InfiniteHorizontalGrid {
+  cells: DenseGrid<bool>,
+}
+
+impl InfiniteHorizontalGrid {
+  pub fn value_at(&self, row: isize, col: isize) -> Option<bool> {
+    self.cells.get((col, row)).cloned()
   }
 
   pub fn taken_on_slope(&self, dx: i64, dy: i64) -> u64 {
-    let mut x = 0;
-    let mut y = 0;
-    let mut count: u64 = 0;
+    let path = self.path_on_slope((0, 0), Slope { dx, dy });
+    path.iter().filter(|(_, tree)| *tree).count() as u64
+  }
 
-    while let Some(tree_present) = self.value_at(y, x) {
-      if tree_present {
-        count += 1;
-      }
+  // Each position visited from `start` until going off the bottom of the map,
+  // and whether it holds a tree. Positions aren't wrapped, so x keeps counting
+  // past the edge of the map. `slope.dy` must be positive, or the bottom of
+  // the map is never reached.
+  fn path_on_slope(&self, start: Coord, slope: Slope) -> Vec<(Coord, bool)> {
+    assert!(slope.dy > 0, "Slope must move down, got {}", slope);
+    let (dx, dy) = (slope.dx as isize, slope.dy as isize);
+
+    iter::successors(Some(start), |&(x, y)| Some((x + dx, y + dy)))
+      .map_while(|(x, y)| self.value_at(y, x).map(|tree| ((x, y), tree)))
+      .collect()
+  }
+
+  // The map, repeated sideways in whole copies as far as `path` goes, with `X`
+  // over trees on the path and `O` over open squares
+  fn render_path(&self, path: &[(Coord, bool)]) -> String {
+    let bounds = match self.cells.extent() {
+      Some(bounds) => bounds,
+      None => return String::new(),
+    };
+    let width = bounds.width() as isize;
+    let xs = path.iter().map(|&((x, _), _)| x);
+    let left = xs.clone().min().unwrap_or(0).min(0).div_euclid(width) * width;
+    let right = (xs.max().unwrap_or(0).max(0).div_euclid(width) + 1) * width;
+    let on_path: HashMap<Coord, bool> = path.iter().cloned().collect();
 
-      x += dx as usize;
-      y += dy as usize;
+    let mut output = String::new();
+    for y in bounds.min.1..=bounds.max.1 {
+      for x in left..right {
+        output.push(match (on_path.get(&(x, y)), self.value_at(y, x)) {
+          (Some(true), _) => 'X',
+          (Some(false), _) => 'O',
+          (None, Some(true)) => '#',
+          (None, _) => '.',
+        });
+      }
+      output.push('\n');
     }
 
-    count
+    output
+  }
+
+  // Every slope with |dx| and dy up to `bound`, from fewest trees to most
+  fn rank_slopes(&self, bound: i64) -> Vec<(Slope, u64)> {
+    let mut ranked: Vec<(Slope, u64)> = (1..=bound)
+      .flat_map(|dy| (-bound..=bound).map(move |dx| Slope { dx, dy }))
+      .map(|slope| (slope, self.taken_on_slope(slope.dx, slope.dy)))
+      .collect();
+    ranked.sort_by_key(|&(slope, trees)| (trees, slope));
+
+    ranked
   }
 }
 
@@ -58,16 +271,14 @@ impl FromStr for InfiniteHorizontalGrid {
   type Err = String;
 
   fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-    let lines: Vec<Vec<bool>> = s
-      .lines()
-      .map(|line| line.chars().map(|c| c == '#').collect())
-      .collect();
+    let mut cells: DenseGrid<bool> = DenseGrid::from_str_with(s, RowOrder::YDown, |c| match c {
+      '#' => Ok(true),
+      '.' => Ok(false),
+      _ => Err(format!("Unexpected character in map: {}", c)),
+    })?;
+    cells.set_boundary(0, Boundary::Wrap);
 
-    Ok(Self {
-      width: lines[0].len(),
-      height: lines.len(),
-      lines,
-    })
+    Ok(Self { cells })
   }
 }
 
@@ -87,3 +298,97 @@ fn test_horizontal_grid() {
   assert_eq!(grid.taken_on_slope(7, 1), 4);
   assert_eq!(grid.taken_on_slope(1, 2), 2);
 }
+
+#[test]
+fn test_slopes() {
+  let input = super::common::get_input("03_sample").unwrap();
+  let grid: InfiniteHorizontalGrid = input.parse().unwrap();
+
+  assert_eq!("3,1".parse(), Ok(Slope { dx: 3, dy: 1 }));
+  assert_eq!(" -2, 3".parse(), Ok(Slope { dx: -2, dy: 3 }));
+  assert!("3,0".parse::<Slope>().is_err());
+  assert!("3,x".parse::<Slope>().is_err());
+  assert!("3,1,2".parse::<Slope>().is_err());
+
+  // The map wraps, so moving left works too
+  assert_eq!(grid.taken_on_slope(-3, 1), grid.taken_on_slope(8, 1));
+
+  let ranked = grid.rank_slopes(3);
+  assert_eq!(ranked.len(), 21);
+  assert!(ranked.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+  assert!(ranked.contains(&(Slope { dx: 3, dy: 1 }, 7)));
+  assert!(ranked.contains(&(Slope { dx: 1, dy: 2 }, 2)));
+  assert_eq!(ranked.last().unwrap().1, 7);
+}
+
+#[test]
+fn test_paths() {
+  let input = super::common::get_input("03_sample").unwrap();
+  let grid: InfiniteHorizontalGrid = input.parse().unwrap();
+
+  assert_eq!("1/2".parse(), Ok(Slope { dx: 1, dy: 2 }));
+  assert_eq!("6/4".parse(), Ok(Slope { dx: 3, dy: 2 }));
+  assert_eq!("3/-1".parse(), Ok(Slope { dx: -3, dy: 1 }));
+  assert_eq!("0.25".parse(), Ok(Slope { dx: 1, dy: 4 }));
+  assert_eq!("-1.5".parse(), Ok(Slope { dx: -3, dy: 2 }));
+  assert_eq!("3".parse(), Ok(Slope { dx: 3, dy: 1 }));
+  assert_eq!("0".parse(), Ok(Slope { dx: 0, dy: 1 }));
+  assert!("1/0".parse::<Slope>().is_err());
+  assert!("1.-5".parse::<Slope>().is_err());
+  assert!("a/2".parse::<Slope>().is_err());
+
+  let path = grid.path_on_slope((0, 0), "-1/2".parse().unwrap());
+  let positions: Vec<Coord> = path.iter().map(|&(loc, _)| loc).collect();
+  assert_eq!(
+    positions,
+    vec![(0, 0), (-1, 2), (-2, 4), (-3, 6), (-4, 8), (-5, 10)]
+  );
+  assert_eq!(path.iter().filter(|(_, tree)| *tree).count(), 2);
+
+  let path = grid.path_on_slope((3, 5), Slope { dx: 3, dy: 1 });
+  assert_eq!(path.len(), 6);
+  assert_eq!(path.first(), Some(&((3, 5), grid.value_at(5, 3).unwrap())));
+  assert_eq!(path.last().unwrap().0, (18, 10));
+  assert_eq!(grid.path_on_slope((0, 11), Slope { dx: 1, dy: 1 }), vec![]);
+
+  let args: Vec<String> = vec!["--from".into(), "2,3".into(), "1/3".into(), "--path".into()];
+  let options = parse_args(&args).unwrap();
+  assert_eq!(options.start, (2, 3));
+  assert_eq!(options.slopes, vec![Slope { dx: 1, dy: 3 }]);
+  assert!(options.show_path);
+  assert!(parse_args(&["--from".to_string()]).is_err());
+  assert!(parse_args(&["--from".to_string(), "2".to_string()]).is_err());
+}
+
+#[test]
+fn test_render_path() {
+  use indoc::indoc;
+
+  let input = super::common::get_input("03_sample").unwrap();
+  let grid: InfiniteHorizontalGrid = input.parse().unwrap();
+
+  let path = grid.path_on_slope((0, 0), Slope { dx: 3, dy: 1 });
+  let expected = indoc! {"
+    O.##.........##.........##.......
+    #..O#...#..#...#...#..#...#...#..
+    .#....X..#..#....#..#..#....#..#.
+    ..#.#...#O#..#.#...#.#..#.#...#.#
+    .#...##..#..X...##..#..#...##..#.
+    ..#.##.......#.X#.......#.##.....
+    .#.#.#....#.#.#.#.O..#.#.#.#....#
+    .#........#.#........X.#........#
+    #.##...#...#.##...#...#.X#...#...
+    #...##....##...##....##...#X....#
+    .#..#...#.#.#..#...#.#.#..#...X.#
+  "};
+  assert_eq!(grid.render_path(&path), expected);
+
+  // Going left adds copies of the map on the left
+  let path = grid.path_on_slope((1, 0), Slope { dx: -1, dy: 5 });
+  let rendered = grid.render_path(&path);
+  let lines: Vec<&str> = rendered.lines().collect();
+  assert_eq!(lines.len(), 11);
+  assert_eq!(lines[0], "..##........O##.......");
+  assert_eq!(lines[5], "..#.##.....O.#.##.....");
+  assert_eq!(lines[10], ".#..#...#.X.#..#...#.#");
+}
@@ -1,17 +1,32 @@
+use super::common;
 use regex::Regex;
 use std::collections::HashMap;
+use std::fs;
+use std::ops::RangeInclusive;
 use std::str::FromStr;
 
-pub fn run(input: String, _args: &[String]) {
+// Documents are checked against inputs/04_schema.txt, or the schema file given
+// as the first argument
+pub fn run(input: String, args: &[String]) {
+  let schema_text = match args.first() {
+    Some(path) => fs::read_to_string(path),
+    None => common::get_input("04_schema"),
+  }
+  .expect("Couldn't read schema");
+  let schema: Schema = schema_text.parse().unwrap();
+
   let passports: Vec<Passport> = input
     .trim()
     .split("\n\n")
     .map(|parts| parts.parse().unwrap())
     .collect();
-  let required_count = passports.iter().filter(|p| p.has_required_fields()).count();
+  let required_count = passports
+    .iter()
+    .filter(|p| schema.has_required_fields(p))
+    .count();
   println!("Found {} have required fields", required_count);
 
-  let valid_count = passports.iter().filter(|p| p.is_valid()).count();
+  let valid_count = passports.iter().filter(|p| schema.is_valid(p)).count();
   println!("Found {} are fully valid", valid_count);
 }
 
@@ -33,86 +48,159 @@ impl Passport {
   pub fn set(&mut self, key: &str, val: &str) {
     self.data.insert(key.to_string(), val.to_string());
   }
+}
 
-  pub fn has_required_fields(&self) -> bool {
-    lazy_static! {
-      static ref PASSPORT_REQUIRED_FIELDS: Vec<&'static str> =
-        vec!["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid"];
-    }
+// The fields a kind of document may have and what their values must look
+// like. Fields the schema doesn't mention are allowed and never checked.
+struct Schema {
+  fields: Vec<FieldRule>,
+}
+
+struct FieldRule {
+  name: String,
+  required: bool,
+  kind: FieldKind,
+}
 
-    PASSPORT_REQUIRED_FIELDS
+enum FieldKind {
+  Any,
+  Int(RangeInclusive<i64>),
+  Measure(Vec<(String, RangeInclusive<i64>)>),
+  Enum(Vec<String>),
+  Pattern(Regex),
+}
+
+impl Schema {
+  pub fn has_required_fields(&self, doc: &Passport) -> bool {
+    self
+      .fields
       .iter()
-      .all(|key| self.data.contains_key(&key.to_string()))
+      .all(|field| !field.required || doc.get(&field.name).is_some())
   }
 
-  pub fn is_valid(&self) -> bool {
-    lazy_static! {
-      static ref PASSPORT_HEIGHT_REGEX: Regex = Regex::new(r"^(\d+)(cm|in)$").unwrap();
-      static ref PASSPORT_HAIR_REGEX: Regex = Regex::new(r"^#[a-f0-9]{6}$").unwrap();
-      static ref PASSPORT_EYE_REGEX: Regex =
-        Regex::new(r"^(amb|blu|brn|gry|grn|hzl|oth)$").unwrap();
-      static ref PASSPORT_ID_REGEX: Regex = Regex::new(r"^[0-9]{9}$").unwrap();
-    }
+  pub fn is_valid(&self, doc: &Passport) -> bool {
+    self.check(doc).is_ok()
+  }
 
-    if !self.has_required_fields() {
-      return false;
+  // Explains the first field that's missing or has a bad value
+  pub fn check(&self, doc: &Passport) -> Result<(), String> {
+    for field in self.fields.iter() {
+      match doc.get(&field.name) {
+        None if field.required => return Err(format!("Missing {}", field.name)),
+        Some(value) if !field.kind.accepts(value) => {
+          return Err(format!("Bad value for {}: {}", field.name, value))
+        }
+        _ => (),
+      }
     }
 
-    let byr = self.get("byr").unwrap();
-    let byr_val: u32 = byr.parse().unwrap();
-    if byr_val < 1920 || byr_val > 2002 {
-      return false;
-    }
+    Ok(())
+  }
+}
 
-    let iyr = self.get("iyr").unwrap();
-    let iyr_val: u32 = iyr.parse().unwrap();
-    if iyr_val < 2010 || iyr_val > 2020 {
-      return false;
+impl FieldKind {
+  fn accepts(&self, value: &str) -> bool {
+    match self {
+      FieldKind::Any => true,
+      FieldKind::Int(range) => value.parse().is_ok_and(|num| range.contains(&num)),
+      FieldKind::Measure(units) => {
+        let split = value
+          .char_indices()
+          .find(|&(idx, c)| !(c.is_ascii_digit() || (idx == 0 && c == '-')))
+          .map_or(value.len(), |(idx, _)| idx);
+        let (num, unit) = value.split_at(split);
+        match num.parse::<i64>() {
+          Ok(num) => units
+            .iter()
+            .any(|(name, range)| name == unit && range.contains(&num)),
+          Err(_) => false,
+        }
+      }
+      FieldKind::Enum(options) => options.iter().any(|option| option == value),
+      FieldKind::Pattern(re) => re.is_match(value),
     }
+  }
+}
 
-    let eyr = self.get("eyr").unwrap();
-    let eyr_val: u32 = eyr.parse().unwrap();
-    if eyr_val < 2020 || eyr_val > 2030 {
-      return false;
-    }
+// One field per line, as `<field> required|optional <type> [args...]`; see
+// inputs/04_schema.txt. Blank lines and lines starting with `#` are ignored.
+impl FromStr for Schema {
+  type Err = String;
 
-    let hgt = self.get("hgt").unwrap();
-    if !PASSPORT_HEIGHT_REGEX.is_match(hgt) {
-      return false;
-    }
-    let caps = PASSPORT_HEIGHT_REGEX.captures(hgt).unwrap();
-    let num: u32 = caps.get(1).unwrap().as_str().parse().unwrap();
-    let unit = caps.get(2).unwrap().as_str();
-    match unit {
-      "cm" => {
-        if num < 150 || num > 193 {
-          return false;
-        }
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let fields = s
+      .lines()
+      .map(|line| line.trim())
+      .filter(|line| !line.is_empty() && !line.starts_with('#'))
+      .map(|line| line.parse())
+      .collect::<Result<_, _>>()?;
+
+    Ok(Self { fields })
+  }
+}
+
+impl FromStr for FieldRule {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let parts: Vec<&str> = s.split_whitespace().collect();
+    let (name, required, kind, args) = match parts[..] {
+      [name, required, kind, ref args @ ..] => (name, required, kind, args),
+      _ => return Err(format!("Expected a name, requirement and type: {}", s)),
+    };
+
+    let required = match required {
+      "required" => true,
+      "optional" => false,
+      _ => return Err(format!("Expected required or optional: {}", s)),
+    };
+
+    let kind = match (kind, args) {
+      ("any", []) => FieldKind::Any,
+      ("int", [range]) => FieldKind::Int(parse_range(range)?),
+      ("measure", units) if !units.is_empty() && units.len() % 2 == 0 => FieldKind::Measure(
+        units
+          .chunks(2)
+          .map(|pair| Ok((pair[0].to_string(), parse_range(pair[1])?)))
+          .collect::<Result<_, String>>()?,
+      ),
+      ("enum", options) if !options.is_empty() => {
+        FieldKind::Enum(options.iter().map(|option| option.to_string()).collect())
       }
-      _ => {
-        if num < 59 || num > 76 {
-          return false;
-        }
+      // A pattern can contain spaces, so it runs to the end of the line
+      ("regex", [_, ..]) => {
+        let pattern = skip_words(s, 3).trim_end();
+        FieldKind::Pattern(
+          Regex::new(pattern).map_err(|err| format!("Bad regex {}: {}", pattern, err))?,
+        )
       }
-    }
+      _ => return Err(format!("Could not parse field type: {}", s)),
+    };
 
-    let hcl = self.get("hcl").unwrap();
-    if !PASSPORT_HAIR_REGEX.is_match(hcl) {
-      return false;
-    }
+    Ok(Self {
+      name: name.to_string(),
+      required,
+      kind,
+    })
+  }
+}
 
-    let ecl = self.get("ecl").unwrap();
-    if !PASSPORT_EYE_REGEX.is_match(ecl) {
-      return false;
-    }
+// What's left of `s` after its first `n` whitespace-separated words
+fn skip_words(s: &str, n: usize) -> &str {
+  (0..n).fold(s.trim_start(), |rest, _| {
+    rest
+      .trim_start_matches(|c: char| !c.is_whitespace())
+      .trim_start()
+  })
+}
 
-    let pid = self.get("pid").unwrap();
-    if !PASSPORT_ID_REGEX.is_match(pid) {
-      return false;
-    }
+fn parse_range(s: &str) -> Result<RangeInclusive<i64>, String> {
+  let bad_range = || format!("Could not parse range: {}", s);
+  let idx = s.find("..=").ok_or_else(bad_range)?;
+  let min = s[..idx].parse().map_err(|_| bad_range())?;
+  let max = s[idx + 3..].parse().map_err(|_| bad_range())?;
 
-    true
-  }
+  Ok(min..=max)
 }
 
 impl FromStr for Passport {
@@ -141,8 +229,7 @@ impl FromStr for Passport {
 
 #[test]
 fn test_passport() {
-  use super::common;
-
+  let schema: Schema = common::get_input("04_schema").unwrap().parse().unwrap();
   let input = common::get_input("04_sample").unwrap();
   let passport: Passport =
     "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd\nbyr:1937 iyr:2017 cid:147 hgt:183cm"
@@ -155,7 +242,7 @@ fn test_passport() {
     .split("\n\n")
     .map(|parts| parts.parse().unwrap())
     .collect();
-  let num_valid = passports.iter().filter(|p| p.is_valid()).count();
+  let num_valid = passports.iter().filter(|p| schema.is_valid(p)).count();
   assert_eq!(num_valid, 2);
 
   let input = common::get_input("04_sample_valid").unwrap();
@@ -163,12 +250,68 @@ fn test_passport() {
     .split("\n\n")
     .map(|parts| parts.parse().unwrap())
     .collect();
-  assert_eq!(passports.iter().all(|p| p.is_valid()), true);
+  assert_eq!(passports.iter().all(|p| schema.is_valid(p)), true);
 
   let input = common::get_input("04_sample_invalid").unwrap();
   let passports: Vec<Passport> = input
     .split("\n\n")
     .map(|parts| parts.parse().unwrap())
     .collect();
-  assert_eq!(passports.iter().all(|p| !p.is_valid()), true);
+  assert_eq!(passports.iter().all(|p| !schema.is_valid(p)), true);
+}
+
+#[test]
+fn test_schema() {
+  use indoc::indoc;
+
+  let schema: Schema = indoc! {"
+    # North Pole credentials
+    id required regex ^NP[0-9]{4}$
+    clearance required enum elf reindeer santa
+    shoe optional measure eu 30..=50 us -2..=15
+    age optional int 0..=2000
+    note optional any
+  "}
+  .parse()
+  .unwrap();
+
+  let doc: Passport = "id:NP1234 clearance:elf shoe:42eu age:1500"
+    .parse()
+    .unwrap();
+  assert!(schema.has_required_fields(&doc));
+  assert_eq!(schema.check(&doc), Ok(()));
+
+  let doc: Passport = "id:NP1234 clearance:elf shoe:-1us note:hi".parse().unwrap();
+  assert_eq!(schema.check(&doc), Ok(()));
+  let doc: Passport = "id:NP1234 clearance:elf shoe:42".parse().unwrap();
+  assert_eq!(
+    schema.check(&doc),
+    Err("Bad value for shoe: 42".to_string())
+  );
+  let doc: Passport = "id:NP1234 clearance:elf shoe:20us".parse().unwrap();
+  assert!(!schema.is_valid(&doc));
+  let doc: Passport = "id:NP1234 clearance:elf age:old".parse().unwrap();
+  assert!(!schema.is_valid(&doc));
+  let doc: Passport = "id:NP123 clearance:dwarf".parse().unwrap();
+  assert!(schema.has_required_fields(&doc));
+  assert_eq!(
+    schema.check(&doc),
+    Err("Bad value for id: NP123".to_string())
+  );
+  let doc: Passport = "clearance:santa byr:1920".parse().unwrap();
+  assert!(!schema.has_required_fields(&doc));
+  assert_eq!(schema.check(&doc), Err("Missing id".to_string()));
+
+  assert!("a required".parse::<Schema>().is_err());
+  assert!("a maybe any".parse::<Schema>().is_err());
+  assert!("a required int 1..5".parse::<Schema>().is_err());
+  assert!("a required measure cm".parse::<Schema>().is_err());
+  assert!("a required enum".parse::<Schema>().is_err());
+  assert!("a required regex (".parse::<Schema>().is_err());
+  assert!("a required regex ".parse::<Schema>().is_err());
+
+  let rule: FieldRule = "motto optional  regex  ^ho( ho)*  !$".parse().unwrap();
+  assert!(rule.kind.accepts("ho ho ho  !"));
+  assert!(!rule.kind.accepts("ho ho ho !"));
+  assert!("a required color".parse::<Schema>().is_err());
 }
@@ -1,23 +1,26 @@
+use super::automaton::{self, Automaton};
 use super::coords::{Coordinate, Direction};
-use super::grid::Grid;
+use super::grid::{DenseGrid, GridStorage, RowOrder};
+use std::iter;
 use std::str::FromStr;
 use strum::IntoEnumIterator;
 
 type Coord = (isize, isize);
+type Seats = DenseGrid<SeatType>;
 
 pub fn run(input: String, _args: &[String]) {
   let mut layout: Layout = input.parse().unwrap();
-  while layout.tick(Rules::Part1) != 0 {}
+  layout.settle(Rules::Part1);
   let count = layout.count_seated();
   println!("{} people are seated.", count);
 
   let mut layout: Layout = input.parse().unwrap();
-  while layout.tick(Rules::Part2) != 0 {}
+  layout.settle(Rules::Part2);
   let count = layout.count_seated();
   println!("With the modified rules, {} people are seated.", count);
 }
 
-#[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq, Eq, Hash)]
 enum SeatType {
   FLOOR,
   EMPTY,
@@ -43,15 +46,23 @@ enum Rules {
   Part2,
 }
 
-fn part_1_rules(layout: &Layout, coord: Coord, current: SeatType) -> Option<SeatType> {
+impl Rules {
+  fn apply(self, seats: &Seats, coord: Coord, current: &SeatType) -> Option<SeatType> {
+    match self {
+      Rules::Part1 => part_1_rules(seats, coord, *current),
+      Rules::Part2 => part_2_rules(seats, coord, *current),
+    }
+  }
+}
+
+fn part_1_rules(seats: &Seats, coord: Coord, current: SeatType) -> Option<SeatType> {
   if current == SeatType::FLOOR {
     return None;
   }
 
   let occupied_adjacent = coord
     .neighbors()
-    .iter()
-    .map(|c| layout.items.get(c))
+    .map(|c| seats.get(c))
     .filter(|&v| matches!(v, Some(&s) if s == SeatType::OCCUPIED))
     .count();
 
@@ -62,15 +73,14 @@ fn part_1_rules(layout: &Layout, coord: Coord, current: SeatType) -> Option<Seat
   }
 }
 
-fn part_2_rules(layout: &Layout, coord: Coord, current: SeatType) -> Option<SeatType> {
+fn part_2_rules(seats: &Seats, coord: Coord, current: SeatType) -> Option<SeatType> {
   if current == SeatType::FLOOR {
     return None;
   }
 
-  let visible_taken = layout
-    .seen_from(coord)
+  let visible_taken = seen_from(seats, coord)
     .iter()
-    .filter(|&coord| matches!(layout.items.get(coord), Some(SeatType::OCCUPIED)))
+    .filter(|&coord| matches!(seats.get(coord), Some(SeatType::OCCUPIED)))
     .count();
 
   match (current, visible_taken) {
@@ -80,52 +90,40 @@ fn part_2_rules(layout: &Layout, coord: Coord, current: SeatType) -> Option<Seat
   }
 }
 
+fn seen_from(seats: &Seats, coord: Coord) -> Vec<Coord> {
+  Direction::iter()
+    .filter_map(|dir| seats.first_visible(coord, dir, |&seat| seat != SeatType::FLOOR))
+    .map(|(coord, _)| coord)
+    .collect()
+}
+
 #[derive(Debug, Clone)]
 struct Layout {
-  items: Grid<SeatType>,
+  items: DenseGrid<SeatType>,
 }
 
 impl Layout {
-  fn new() -> Self {
-    Self { items: Grid::new() }
-  }
-
+  #[allow(dead_code)]
   fn tick(&mut self, rules: Rules) -> usize {
-    let mut changes: Vec<(Coord, SeatType)> = vec![];
-
-    for (&key, &value) in self.items.iter() {
-      let change = match rules {
-        Rules::Part1 => part_1_rules(&self, key, value),
-        Rules::Part2 => part_2_rules(&self, key, value),
-      };
-
-      if let Some(new_value) = change {
-        changes.push((key, new_value));
-      }
-    }
+    let (next, changes) = automaton::next_generation(&self.items, |seats, coord, current| {
+      rules.apply(seats, coord, current)
+    });
+    self.items = next;
 
-    for &(coord, new_state) in changes.iter() {
-      self.items.insert(coord, new_state);
-    }
-
-    changes.len()
+    changes
   }
 
-  fn seen_from(&self, coord: Coord) -> Vec<Coord> {
-    Direction::iter()
-      .map(|dir| {
-        coord
-          .toward(dir)
-          // Coordinates outside of our grid's working space will return `None`
-          .take_while(|coord| self.items.get(coord).is_some())
-          .find(|coord| {
-            let item = self.items.get(coord);
-            matches!(item, Some(SeatType::OCCUPIED) | Some(SeatType::EMPTY))
-          })
-      })
-      .filter(Option::is_some)
-      .map(Option::unwrap)
-      .collect()
+  fn settle(&mut self, rules: Rules) {
+    let automaton = Automaton::new(self.items.clone());
+    // Only adjacent seats matter in part 1, but part 2 can see across the room
+    let mut automaton = match rules {
+      Rules::Part1 => {
+        automaton.only_near_changes(|coord| coord.neighbors().chain(iter::once(coord)).collect())
+      }
+      Rules::Part2 => automaton,
+    };
+    automaton.run(|seats, coord, current| rules.apply(seats, coord, current));
+    self.items = automaton.into_grid();
   }
 
   fn count_seated(&self) -> usize {
@@ -141,18 +139,8 @@ impl FromStr for Layout {
   type Err = String;
 
   fn from_str(s: &str) -> Result<Self, Self::Err> {
-    let mut layout = Layout::new();
-
-    for (row, line) in s.lines().enumerate() {
-      for (col, chr) in line.chars().enumerate() {
-        let seat: SeatType = chr.to_string().parse()?;
-        let x = col as isize;
-        let y = row as isize;
-        layout.items.insert((x, y), seat);
-      }
-    }
-
-    Ok(layout)
+    let items = DenseGrid::from_str_with(s, RowOrder::YDown, |chr| chr.to_string().parse())?;
+    Ok(Self { items })
   }
 }
 
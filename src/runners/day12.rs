@@ -1,4 +1,4 @@
-use super::coords::Direction;
+use super::coords::{Direction, Planar, Point};
 use std::str::FromStr;
 
 pub fn run(input: String, _args: &[String]) {
@@ -10,7 +10,7 @@ pub fn run(input: String, _args: &[String]) {
 
   println!(
     "Manhattan distance: {}",
-    manhattan_distance_from_origin(ship.pos)
+    ship.pos.manhattan(Point::default())
   );
 
   let instructions: Vec<Instruction> = input.lines().map(|line| line.parse().unwrap()).collect();
@@ -21,76 +21,56 @@ pub fn run(input: String, _args: &[String]) {
 
   println!(
     "Manhattan distance with improved navigation: {}",
-    manhattan_distance_from_origin(ship.pos)
+    ship.pos.manhattan(Point::default())
   );
 }
 
 #[derive(Debug)]
 struct Ship {
-  pos: (isize, isize),
-  waypoint: (isize, isize),
+  pos: Point,
+  waypoint: Point,
   facing: Direction,
 }
 
 impl Ship {
   fn new() -> Self {
     Ship {
-      pos: (0, 0),
-      waypoint: (10, 1),
+      pos: Point::default(),
+      waypoint: Point::new(10, 1),
       facing: Direction::E,
     }
   }
 
   fn navigate(&mut self, instr: &Instruction) {
-    match instr {
-      Instruction::N(amount) => self.pos.1 += amount,
-      Instruction::S(amount) => self.pos.1 -= amount,
-      Instruction::E(amount) => self.pos.0 += amount,
-      Instruction::W(amount) => self.pos.0 -= amount,
-      Instruction::L(degrees) => {
-        let count = degrees / 90;
-        for _ in 0..count {
-          self.facing = self.facing.left_90();
-        }
+    match *instr {
+      Instruction::Move(direction, amount) => {
+        self.pos = self.pos + Point::from(direction) * amount;
       }
-      Instruction::R(degrees) => {
-        let count = degrees / 90;
-        for _ in 0..count {
-          self.facing = self.facing.right_90();
-        }
+      Instruction::Turn(degrees) => {
+        self.facing = self
+          .facing
+          .rotate(degrees)
+          .expect("Turns are validated when parsing");
       }
-      Instruction::F(units) => {
-        let (x, y) = self.facing.delta();
-        self.pos = (self.pos.0 + x * units, self.pos.1 + y * units);
+      Instruction::Forward(units) => {
+        self.pos = self.pos + Point::from(self.facing) * units;
       }
     }
   }
 
   fn navigate_part_2(&mut self, instr: &Instruction) {
-    match instr {
-      Instruction::N(amount) => self.waypoint.1 += amount,
-      Instruction::S(amount) => self.waypoint.1 -= amount,
-      Instruction::E(amount) => self.waypoint.0 += amount,
-      Instruction::W(amount) => self.waypoint.0 -= amount,
-      Instruction::L(degrees) => {
-        let count = degrees / 90;
-        for _ in 0..count {
-          let (x, y) = (-self.waypoint.1, self.waypoint.0);
-          self.waypoint = (x, y);
-        }
+    match *instr {
+      Instruction::Move(direction, amount) => {
+        self.waypoint = self.waypoint + Point::from(direction) * amount;
       }
-      Instruction::R(degrees) => {
-        let count = degrees / 90;
-        for _ in 0..count {
-          let (x, y) = (self.waypoint.1, -self.waypoint.0);
-          self.waypoint = (x, y);
-        }
+      Instruction::Turn(degrees) => {
+        self.waypoint = self
+          .waypoint
+          .rotate(degrees)
+          .expect("Turns are validated when parsing");
       }
-      Instruction::F(units) => {
-        let dx = self.waypoint.0 * units;
-        let dy = self.waypoint.1 * units;
-        let (x, y) = self.pos;
-        self.pos = (x + dx, y + dy);
+      Instruction::Forward(units) => {
+        self.pos = self.pos + self.waypoint * units;
       }
     }
   }
@@ -98,13 +78,10 @@ impl Ship {
 
 #[derive(Debug)]
 enum Instruction {
-  N(isize),
-  S(isize),
-  E(isize),
-  W(isize),
-  L(isize),
-  R(isize),
-  F(isize),
+  Move(Direction, isize),
+  // Positive degrees turn clockwise
+  Turn(isize),
+  Forward(isize),
 }
 
 impl FromStr for Instruction {
@@ -112,27 +89,22 @@ impl FromStr for Instruction {
 
   fn from_str(s: &str) -> Result<Self, Self::Err> {
     let mut chars = s.chars();
-    let kind = chars.next().unwrap();
+    let kind = chars.next().ok_or("Empty instruction")?;
     let rest: String = chars.collect();
-    let num: isize = rest.parse().unwrap();
+    let num: isize = rest
+      .parse()
+      .map_err(|_| format!("Could not parse instruction: {}", s))?;
 
     match kind {
-      'N' => Ok(Instruction::N(num)),
-      'S' => Ok(Instruction::S(num)),
-      'E' => Ok(Instruction::E(num)),
-      'W' => Ok(Instruction::W(num)),
-      'L' => Ok(Instruction::L(num)),
-      'R' => Ok(Instruction::R(num)),
-      'F' => Ok(Instruction::F(num)),
-      _ => Err(format!("Could not parse instruction: {}", s)),
+      'L' | 'R' if num % 90 != 0 => Err(format!("Turns must be multiples of 90 degrees: {}", s)),
+      'L' => Ok(Instruction::Turn(-num)),
+      'R' => Ok(Instruction::Turn(num)),
+      'F' => Ok(Instruction::Forward(num)),
+      _ => Ok(Instruction::Move(Direction::from_char(kind)?, num)),
     }
   }
 }
 
-fn manhattan_distance_from_origin(coord: (isize, isize)) -> isize {
-  coord.0.abs() + coord.1.abs()
-}
-
 #[test]
 fn test_ship() {
   let input = "F10\nN3\nF7\nR90\nF11";
@@ -143,14 +115,17 @@ fn test_ship() {
     ship.navigate(instr);
   }
 
-  assert_eq!(ship.pos, (17, -8));
-  assert_eq!(manhattan_distance_from_origin(ship.pos), 25);
+  assert_eq!(ship.pos, Point::new(17, -8));
+  assert_eq!(ship.pos.manhattan(Point::default()), 25);
 
   let mut ship = Ship::new();
   for instr in instructions.iter() {
     ship.navigate_part_2(instr);
   }
 
-  assert_eq!(ship.pos, (214, -72));
-  assert_eq!(manhattan_distance_from_origin(ship.pos), 286);
+  assert_eq!(ship.pos, Point::new(214, -72));
+  assert_eq!(ship.pos.manhattan(Point::default()), 286);
+
+  assert!("R45".parse::<Instruction>().is_err());
+  assert!("X10".parse::<Instruction>().is_err());
 }
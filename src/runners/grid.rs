@@ -1,57 +1,898 @@
+use super::coords::{Coordinate, Direction, Neighborhood, Planar};
 use std::borrow::Borrow;
 use std::cmp;
-use std::collections::hash_map;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
+
+pub type Coord = (isize, isize);
+
+// How the lines of a character map relate to the y axis. `YDown` puts the first
+// line at y = 0 and counts up going down the page, matching most puzzle inputs;
+// `YUp` puts the last line at y = 0 so that north (+y) is up the page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum RowOrder {
+  YDown,
+  YUp,
+}
+
+// Grids of more than two dimensions still parse and render character maps, as
+// the x/y plane through the origin.
+pub trait GridStorage<V, C: Coordinate = Coord> {
+  fn with_size(width: usize, height: usize) -> Self
+  where
+    Self: Sized;
+  fn get<T>(&self, loc: T) -> Option<&V>
+  where
+    T: Borrow<C>;
+  fn insert(&mut self, loc: C, value: V);
+  // Both iterators visit cells in row-major order: along x, then down y
+  fn iter(&self) -> Box<dyn Iterator<Item = (C, &V)> + '_>;
+  #[allow(dead_code)]
+  fn iter_mut(&mut self) -> Box<dyn Iterator<Item = (C, &mut V)> + '_>;
+  fn extent(&self) -> Option<Bounds<C>>;
+  fn set_boundary(&mut self, axis: usize, boundary: Boundary);
+  #[allow(dead_code)]
+  fn set_default(&mut self, value: V);
+  // The value read past an `Infinite` boundary, if one has been set
+  fn default_value(&self) -> Option<&V>;
+  // Where reading `loc` actually looks inside the grid, after wrapping. `None`
+  // for anywhere past a bounded or infinite edge.
+  fn locate(&self, loc: C) -> Option<C>;
+
+  #[allow(dead_code)]
+  fn get_extent(&self, extent: Extent) -> isize {
+    match (self.extent(), extent) {
+      (None, _) => 0,
+      (Some(bounds), Extent::TOP) => bounds.max.component(1),
+      (Some(bounds), Extent::RIGHT) => bounds.max.component(0),
+      (Some(bounds), Extent::BOTTOM) => bounds.min.component(1),
+      (Some(bounds), Extent::LEFT) => bounds.min.component(0),
+    }
+  }
+
+  #[allow(dead_code)]
+  fn iter_column_major<'a>(&'a self) -> Box<dyn Iterator<Item = (C, &'a V)> + 'a>
+  where
+    C: 'a,
+  {
+    let mut cells: Vec<_> = self.iter().collect();
+    cells.sort_by(|(a, _), (b, _)| column_major(a, b));
+    Box::new(cells.into_iter())
+  }
+
+  // The cells of each row, top to bottom. Rows only hold the cells that exist,
+  // and rows with no cells at all are skipped.
+  #[allow(dead_code)]
+  fn rows<'a>(&'a self) -> Box<dyn Iterator<Item = Vec<(C, &'a V)>> + 'a>
+  where
+    C: 'a,
+  {
+    Box::new(group_lines(self.iter(), 0).into_iter())
+  }
+
+  #[allow(dead_code)]
+  fn cols<'a>(&'a self) -> Box<dyn Iterator<Item = Vec<(C, &'a V)>> + 'a>
+  where
+    C: 'a,
+  {
+    Box::new(group_lines(self.iter_column_major(), 1).into_iter())
+  }
+
+  fn from_str_with<F, E>(s: &str, order: RowOrder, mut mapper: F) -> Result<Self, E>
+  where
+    Self: Sized,
+    F: FnMut(char) -> Result<V, E>,
+  {
+    let lines: Vec<&str> = s.lines().collect();
+    let width = lines.iter().map(|line| line.chars().count()).max();
+    let height = lines.len();
+    let mut grid = Self::with_size(width.unwrap_or(0), height);
+
+    for (row, line) in lines.iter().enumerate() {
+      let y = match order {
+        RowOrder::YDown => row as isize,
+        RowOrder::YUp => (height - row - 1) as isize,
+      };
+
+      for (col, chr) in line.chars().enumerate() {
+        grid.insert(C::from_xy(col as isize, y), mapper(chr)?);
+      }
+    }
+
+    Ok(grid)
+  }
+
+  #[allow(dead_code)]
+  fn render<F>(&self, order: RowOrder, mapper: F) -> String
+  where
+    Self: Sized,
+    F: Fn(Option<&V>) -> char,
+  {
+    render_cells(self, order, |cell| mapper(cell).to_string())
+  }
+
+  // Every coordinate from `from` (exclusive) in `direction` up to the edge of
+  // the grid's extent, regardless of any wrapping boundary
+  fn ray(&self, from: C, direction: Direction) -> Box<dyn Iterator<Item = C>>
+  where
+    C: Planar + 'static,
+  {
+    let extent = self.extent();
+    Box::new(
+      from
+        .toward(direction)
+        .take_while(move |&loc| extent.is_some_and(|bounds| bounds.contains(loc))),
+    )
+  }
+
+  fn first_visible<F>(&self, from: C, direction: Direction, predicate: F) -> Option<(C, &V)>
+  where
+    C: Planar + 'static,
+    F: Fn(&V) -> bool,
+  {
+    self
+      .ray(from, direction)
+      .filter_map(|loc| self.get(loc).map(|value| (loc, value)))
+      .find(|(_, value)| predicate(value))
+  }
+
+  // The connected cells matching `predicate` that can be reached from `start`,
+  // or `None` if `start` itself doesn't match. Filling never leaves the
+  // grid's extent, even across wrapping or infinite boundaries.
+  #[allow(dead_code)]
+  fn flood_fill<F>(
+    &self,
+    start: C,
+    neighborhood: Neighborhood<C>,
+    predicate: F,
+  ) -> Option<Region<C>>
+  where
+    Self: Sized,
+    F: Fn(&V) -> bool,
+  {
+    fill_region(
+      self,
+      start,
+      neighborhood,
+      &predicate,
+      &mut HashSet::new(),
+      0,
+    )
+  }
+
+  // Every separate region of cells matching `predicate`, labelled from zero in
+  // row-major order of their first cell
+  #[allow(dead_code)]
+  fn components<F>(&self, neighborhood: Neighborhood<C>, predicate: F) -> Vec<Region<C>>
+  where
+    Self: Sized,
+    F: Fn(&V) -> bool,
+  {
+    let cells: Vec<C> = self.iter().map(|(loc, _)| loc).collect();
+
+    let mut seen = HashSet::new();
+    let mut regions = vec![];
+    for loc in cells {
+      if seen.contains(&loc) {
+        continue;
+      }
+
+      let label = regions.len();
+      if let Some(region) = fill_region(self, loc, neighborhood, &predicate, &mut seen, label) {
+        regions.push(region);
+      }
+    }
+
+    regions
+  }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region<C: Coordinate = Coord> {
+  pub label: usize,
+  pub cells: Vec<C>,
+  pub bounds: Bounds<C>,
+}
+
+impl<C: Coordinate> Region<C> {
+  #[allow(dead_code)]
+  pub fn size(&self) -> usize {
+    self.cells.len()
+  }
+}
+
+#[allow(dead_code)]
+fn fill_region<G, V, C, F>(
+  grid: &G,
+  start: C,
+  neighborhood: Neighborhood<C>,
+  predicate: &F,
+  seen: &mut HashSet<C>,
+  label: usize,
+) -> Option<Region<C>>
+where
+  G: GridStorage<V, C>,
+  C: Coordinate,
+  F: Fn(&V) -> bool,
+{
+  let extent = grid.extent()?;
+  let matches = |loc: C| extent.contains(loc) && grid.get(loc).is_some_and(predicate);
+  if !matches(start) || !seen.insert(start) {
+    return None;
+  }
+
+  let mut cells = vec![];
+  let mut bounds = Bounds::new(start, start);
+  let mut stack = vec![start];
+  while let Some(loc) = stack.pop() {
+    cells.push(loc);
+    bounds = bounds.including(loc);
+
+    for next in loc.neighbors_with(neighborhood) {
+      if matches(next) && seen.insert(next) {
+        stack.push(next);
+      }
+    }
+  }
+  cells.sort_by(row_major);
+
+  Some(Region {
+    label,
+    cells,
+    bounds,
+  })
+}
+
+fn row_major<C: Coordinate>(a: &C, b: &C) -> cmp::Ordering {
+  compare_axes(a, b, (0..C::DIMENSIONS).rev())
+}
+
+// Like row-major, but with x and y swapped so that each column is contiguous
+fn column_major<C: Coordinate>(a: &C, b: &C) -> cmp::Ordering {
+  compare_axes(a, b, (2..C::DIMENSIONS).rev().chain([0, 1].iter().copied()))
+}
+
+// Compares by the first axis in `axes` where the two differ
+fn compare_axes<C, I>(a: &C, b: &C, axes: I) -> cmp::Ordering
+where
+  C: Coordinate,
+  I: Iterator<Item = usize>,
+{
+  axes
+    .map(|axis| a.component(axis).cmp(&b.component(axis)))
+    .find(|&order| order != cmp::Ordering::Equal)
+    .unwrap_or(cmp::Ordering::Equal)
+}
+
+// Splits ordered cells into runs that differ only along `axis`
+fn group_lines<'a, C, V, I>(cells: I, axis: usize) -> Vec<Vec<(C, &'a V)>>
+where
+  C: Coordinate,
+  I: Iterator<Item = (C, &'a V)>,
+{
+  let same_line =
+    |a: &C, b: &C| (0..C::DIMENSIONS).all(|i| i == axis || a.component(i) == b.component(i));
+
+  let mut lines: Vec<Vec<(C, &V)>> = vec![];
+  for (loc, value) in cells {
+    match lines.last_mut() {
+      Some(line) if same_line(&line[0].0, &loc) => line.push((loc, value)),
+      _ => lines.push(vec![(loc, value)]),
+    }
+  }
+
+  lines
+}
+
+fn render_cells<G, V, C, F>(grid: &G, order: RowOrder, mapper: F) -> String
+where
+  G: GridStorage<V, C>,
+  C: Coordinate,
+  F: Fn(Option<&V>) -> String,
+{
+  let bounds = match grid.extent() {
+    Some(bounds) => bounds,
+    None => return String::new(),
+  };
+
+  let (min, max) = (bounds.min, bounds.max);
+  let rows: Vec<isize> = match order {
+    RowOrder::YDown => (min.component(1)..=max.component(1)).collect(),
+    RowOrder::YUp => (min.component(1)..=max.component(1)).rev().collect(),
+  };
+
+  let mut output = String::new();
+  for y in rows {
+    for x in min.component(0)..=max.component(0) {
+      output.push_str(&mapper(grid.get(C::from_xy(x, y))));
+    }
+    output.push('\n');
+  }
+
+  output
+}
+
+// An inclusive, axis-aligned box between two corners.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds<C: Coordinate = Coord> {
+  pub min: C,
+  pub max: C,
+}
+
+impl<C: Coordinate> Bounds<C> {
+  pub fn new(a: C, b: C) -> Self {
+    Self {
+      min: a.map_components(|axis, value| cmp::min(value, b.component(axis))),
+      max: a.map_components(|axis, value| cmp::max(value, b.component(axis))),
+    }
+  }
+
+  pub fn including(&self, loc: C) -> Self {
+    Self::new(
+      self
+        .min
+        .map_components(|axis, value| cmp::min(value, loc.component(axis))),
+      self
+        .max
+        .map_components(|axis, value| cmp::max(value, loc.component(axis))),
+    )
+  }
+
+  pub fn size(&self, axis: usize) -> usize {
+    (self.max.component(axis) - self.min.component(axis) + 1) as usize
+  }
+
+  pub fn width(&self) -> usize {
+    self.size(0)
+  }
+
+  pub fn height(&self) -> usize {
+    self.size(1)
+  }
+
+  pub fn contains(&self, loc: C) -> bool {
+    (0..C::DIMENSIONS).all(|axis| {
+      (self.min.component(axis)..=self.max.component(axis)).contains(&loc.component(axis))
+    })
+  }
+}
+
+// What reading a coordinate outside the grid's extent does, chosen per axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Boundary {
+  // There is nothing past the edge
+  #[default]
+  Bounded,
+  // The edge joins up with the opposite edge
+  Wrap,
+  // Everything past the edge reads as the grid's default value
+  #[allow(dead_code)]
+  Infinite,
+}
+
+#[derive(Debug, Clone)]
+struct Edges<V> {
+  axes: Vec<Boundary>,
+  default: Option<V>,
+}
+
+impl<V> Default for Edges<V> {
+  fn default() -> Self {
+    Self {
+      axes: vec![],
+      default: None,
+    }
+  }
+}
+
+enum Lookup<C> {
+  Cell(C),
+  Default,
+  Outside,
+}
+
+impl<V> Edges<V> {
+  fn set(&mut self, axis: usize, boundary: Boundary) {
+    if self.axes.len() <= axis {
+      self.axes.resize(axis + 1, Boundary::Bounded);
+    }
+    self.axes[axis] = boundary;
+  }
+
+  fn resolve<C: Coordinate>(&self, loc: C, extent: Option<Bounds<C>>) -> Lookup<C> {
+    let bounds = match extent {
+      Some(bounds) => bounds,
+      None if self.axes.contains(&Boundary::Infinite) => return Lookup::Default,
+      None => return Lookup::Outside,
+    };
+
+    let mut outside = false;
+    let mut beyond = false;
+    let resolved = loc.map_components(|axis, value| {
+      let policy = self.axes.get(axis).copied().unwrap_or_default();
+      let (min, max) = (bounds.min.component(axis), bounds.max.component(axis));
+      match resolve_axis(policy, value, min, max) {
+        Some(value) => value,
+        None if policy == Boundary::Bounded => {
+          outside = true;
+          value
+        }
+        None => {
+          beyond = true;
+          value
+        }
+      }
+    });
+
+    if outside {
+      Lookup::Outside
+    } else if beyond {
+      Lookup::Default
+    } else {
+      Lookup::Cell(resolved)
+    }
+  }
+
+  fn locate<C: Coordinate>(&self, loc: C, extent: Option<Bounds<C>>) -> Option<C> {
+    match self.resolve(loc, extent) {
+      Lookup::Cell(loc) => Some(loc),
+      Lookup::Default | Lookup::Outside => None,
+    }
+  }
+}
+
+fn resolve_axis(policy: Boundary, value: isize, min: isize, max: isize) -> Option<isize> {
+  match policy {
+    Boundary::Wrap => Some(min + (value - min).rem_euclid(max - min + 1)),
+    _ if (min..=max).contains(&value) => Some(value),
+    _ => None,
+  }
+}
+
+// Renders y-down, one line per row, leaving a space for missing cells
+impl<V: Clone + Display, C: Coordinate> Display for Grid<V, C> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let rendered = render_cells(self, RowOrder::YDown, |cell| {
+      cell.map_or(" ".to_string(), |value| value.to_string())
+    });
+    write!(f, "{}", rendered)
+  }
+}
+
+impl<V: Clone + Display> Display for DenseGrid<V> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let rendered = render_cells(self, RowOrder::YDown, |cell| {
+      cell.map_or(" ".to_string(), |value| value.to_string())
+    });
+    write!(f, "{}", rendered)
+  }
+}
+
+fn mark_extent<C: Coordinate>(extent: &mut Option<Bounds<C>>, loc: C) {
+  *extent = Some(match extent {
+    Some(bounds) => bounds.including(loc),
+    None => Bounds::new(loc, loc),
+  });
+}
 
 #[derive(Debug, Clone, Default)]
-pub struct Grid<V: Clone> {
-  items: HashMap<(isize, isize), V>,
-  extents: [isize; 4],
+pub struct Grid<V: Clone, C: Coordinate = Coord> {
+  items: HashMap<C, V>,
+  extent: Option<Bounds<C>>,
+  edges: Edges<V>,
 }
 
-impl<V: Clone> Grid<V> {
+impl<V: Clone, C: Coordinate> Grid<V, C> {
+  #[allow(dead_code)]
   pub fn new() -> Self {
     Self {
       items: HashMap::new(),
-      extents: [0, 0, 0, 0],
+      extent: None,
+      edges: Default::default(),
     }
   }
+}
+
+// Transformations treat the grid as a picture in y-down order, the way it
+// displays. The result keeps the same top-left corner as the original.
+#[allow(dead_code)]
+impl<V: Clone> Grid<V> {
+  pub fn rotate_cw(&self) -> Self {
+    self.remap(|(x, y), (_, height)| (height - 1 - y, x))
+  }
+
+  pub fn flip_h(&self) -> Self {
+    self.remap(|(x, y), (width, _)| (width - 1 - x, y))
+  }
 
-  pub fn get<T>(&self, loc: T) -> Option<&V>
+  pub fn flip_v(&self) -> Self {
+    self.remap(|(x, y), (_, height)| (x, height - 1 - y))
+  }
+
+  pub fn transpose(&self) -> Self {
+    self.remap(|(x, y), _| (y, x))
+  }
+
+  // The four rotations, followed by the four rotations of the mirror image
+  pub fn all_orientations(&self) -> Vec<Self> {
+    let mut orientations = vec![];
+    for start in [self.clone(), self.flip_h()].iter() {
+      let mut grid = start.clone();
+      for _ in 0..4 {
+        let next = grid.rotate_cw();
+        orientations.push(grid);
+        grid = next;
+      }
+    }
+
+    orientations
+  }
+
+  // The top, right, bottom and left edges, read left to right and top to
+  // bottom, so that touching edges of neighboring tiles compare equal.
+  // Missing cells are skipped.
+  pub fn edges(&self) -> [Vec<V>; 4] {
+    let bounds = match self.extent {
+      Some(bounds) => bounds,
+      None => return [vec![], vec![], vec![], vec![]],
+    };
+    let (min, max) = (bounds.min, bounds.max);
+    let row = |y| (min.0..=max.0).filter_map(move |x| self.items.get(&(x, y)).cloned());
+    let column = |x| (min.1..=max.1).filter_map(move |y| self.items.get(&(x, y)).cloned());
+
+    [
+      row(min.1).collect(),
+      column(max.0).collect(),
+      row(max.1).collect(),
+      column(min.0).collect(),
+    ]
+  }
+
+  // The cells inside `bounds`, at their original coordinates
+  pub fn subgrid(&self, bounds: Bounds) -> Self {
+    let mut grid = Self::new();
+    grid.edges = self.edges.clone();
+    for (&loc, value) in self.items.iter() {
+      if bounds.contains(loc) {
+        grid.insert(loc, value.clone());
+      }
+    }
+
+    grid
+  }
+
+  // Moves every cell by `f`, which maps an offset from the top-left corner and
+  // the grid's (width, height) to a new offset
+  fn remap<F>(&self, f: F) -> Self
   where
-    T: Borrow<(isize, isize)>,
+    F: Fn(Coord, Coord) -> Coord,
   {
-    self.items.get(loc.borrow())
+    let mut grid = Self::new();
+    grid.edges = self.edges.clone();
+    let bounds = match self.extent {
+      Some(bounds) => bounds,
+      None => return grid,
+    };
+
+    let size = (bounds.width() as isize, bounds.height() as isize);
+    let (x0, y0) = bounds.min;
+    for (&(x, y), value) in self.items.iter() {
+      let (dx, dy) = f((x - x0, y - y0), size);
+      grid.insert((x0 + dx, y0 + dy), value.clone());
+    }
+
+    grid
+  }
+}
+
+// Where a pattern matched, as the position of the pattern's top-left corner.
+// `orientation` indexes `all_orientations` of the pattern, and `cells` are
+// the non-wildcard cells it covered.
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternMatch {
+  pub offset: Coord,
+  pub orientation: usize,
+  pub cells: Vec<Coord>,
+}
+
+#[allow(dead_code)]
+impl<V: Clone + PartialEq> Grid<V> {
+  // Every placement of `pattern` whose cells all match, ignoring cells of the
+  // pattern that are missing or equal to `wildcard`. Matches may overlap.
+  pub fn find_pattern(&self, pattern: &Grid<V>, wildcard: Option<&V>) -> Vec<PatternMatch> {
+    self.find_oriented(pattern, 0, wildcard)
+  }
+
+  // As `find_pattern`, trying each distinct orientation of the pattern in turn
+  pub fn find_pattern_any_orientation(
+    &self,
+    pattern: &Grid<V>,
+    wildcard: Option<&V>,
+  ) -> Vec<PatternMatch> {
+    let orientations = pattern.all_orientations();
+    let mut matches = vec![];
+    for (index, oriented) in orientations.iter().enumerate() {
+      // Symmetric patterns would otherwise match the same cells repeatedly
+      if orientations[..index]
+        .iter()
+        .any(|earlier| earlier.items == oriented.items)
+      {
+        continue;
+      }
+      matches.extend(self.find_oriented(oriented, index, wildcard));
+    }
+
+    matches
+  }
+
+  pub fn mark(&mut self, matches: &[PatternMatch], value: V) {
+    for found in matches {
+      for &loc in found.cells.iter() {
+        self.insert(loc, value.clone());
+      }
+    }
   }
 
-  pub fn insert(&mut self, loc: (isize, isize), value: V) {
-    let (x, y) = loc;
-    self.mark_extents(x, y);
+  fn find_oriented(
+    &self,
+    pattern: &Grid<V>,
+    orientation: usize,
+    wildcard: Option<&V>,
+  ) -> Vec<PatternMatch> {
+    let (bounds, pattern_bounds) = match (self.extent, pattern.extent) {
+      (Some(bounds), Some(pattern_bounds)) => (bounds, pattern_bounds),
+      _ => return vec![],
+    };
 
+    let (px, py) = pattern_bounds.min;
+    let mut cells: Vec<(Coord, &V)> = pattern
+      .items
+      .iter()
+      .filter(|&(_, value)| Some(value) != wildcard)
+      .map(|(&(x, y), value)| ((x - px, y - py), value))
+      .collect();
+    cells.sort_by_key(|&(loc, _)| loc);
+
+    let (min, max) = (bounds.min, bounds.max);
+    let last_x = max.0 - pattern_bounds.width() as isize + 1;
+    let last_y = max.1 - pattern_bounds.height() as isize + 1;
+    let mut matches = vec![];
+    for y in min.1..=last_y {
+      for x in min.0..=last_x {
+        let matched = cells
+          .iter()
+          .all(|&((dx, dy), value)| self.items.get(&(x + dx, y + dy)) == Some(value));
+        if matched {
+          matches.push(PatternMatch {
+            offset: (x, y),
+            orientation,
+            cells: cells
+              .iter()
+              .map(|&((dx, dy), _)| (x + dx, y + dy))
+              .collect(),
+          });
+        }
+      }
+    }
+
+    matches
+  }
+}
+
+impl<V: Clone, C: Coordinate> GridStorage<V, C> for Grid<V, C> {
+  fn with_size(width: usize, height: usize) -> Self {
+    Self {
+      items: HashMap::with_capacity(width * height),
+      extent: None,
+      edges: Default::default(),
+    }
+  }
+
+  fn get<T>(&self, loc: T) -> Option<&V>
+  where
+    T: Borrow<C>,
+  {
+    match self.edges.resolve(*loc.borrow(), self.extent) {
+      Lookup::Cell(loc) => self.items.get(&loc),
+      Lookup::Default => self.edges.default.as_ref(),
+      Lookup::Outside => None,
+    }
+  }
+
+  fn insert(&mut self, loc: C, value: V) {
+    mark_extent(&mut self.extent, loc);
     self.items.insert(loc, value);
   }
 
+  fn iter(&self) -> Box<dyn Iterator<Item = (C, &V)> + '_> {
+    let mut cells: Vec<_> = self
+      .items
+      .iter()
+      .map(|(&loc, value)| (loc, value))
+      .collect();
+    cells.sort_by(|(a, _), (b, _)| row_major(a, b));
+    Box::new(cells.into_iter())
+  }
+
+  fn iter_mut(&mut self) -> Box<dyn Iterator<Item = (C, &mut V)> + '_> {
+    let mut cells: Vec<_> = self
+      .items
+      .iter_mut()
+      .map(|(&loc, value)| (loc, value))
+      .collect();
+    cells.sort_by(|(a, _), (b, _)| row_major(a, b));
+    Box::new(cells.into_iter())
+  }
+
+  fn extent(&self) -> Option<Bounds<C>> {
+    self.extent
+  }
+
+  fn set_boundary(&mut self, axis: usize, boundary: Boundary) {
+    self.edges.set(axis, boundary);
+  }
+
+  fn set_default(&mut self, value: V) {
+    self.edges.default = Some(value);
+  }
+
+  fn default_value(&self) -> Option<&V> {
+    self.edges.default.as_ref()
+  }
+
+  fn locate(&self, loc: C) -> Option<C> {
+    self.edges.locate(loc, self.extent)
+  }
+}
+
+// Stores cells in a flat, row-major `Vec`, so lookups are an index calculation
+// rather than a hash. Inserting outside the allocated area grows the storage
+// to fit, but bounded puzzles should size it up front with `with_size`.
+#[derive(Debug, Clone, Default)]
+pub struct DenseGrid<V: Clone> {
+  cells: Vec<Option<V>>,
+  origin: Coord,
+  width: usize,
+  height: usize,
+  extent: Option<Bounds>,
+  edges: Edges<V>,
+}
+
+impl<V: Clone> DenseGrid<V> {
   #[allow(dead_code)]
-  pub fn get_extent(&self, extent: Extent) -> isize {
-    match extent {
-      Extent::TOP => self.extents[0],
-      Extent::RIGHT => self.extents[1],
-      Extent::BOTTOM => self.extents[2],
-      Extent::LEFT => self.extents[3],
+  pub fn new() -> Self {
+    Self::with_size(0, 0)
+  }
+
+  fn index(&self, loc: Coord) -> Option<usize> {
+    let col = loc.0 - self.origin.0;
+    let row = loc.1 - self.origin.1;
+
+    if col < 0 || row < 0 || col as usize >= self.width || row as usize >= self.height {
+      return None;
     }
+
+    Some(row as usize * self.width + col as usize)
   }
 
-  pub fn iter(&self) -> hash_map::Iter<'_, (isize, isize), V> {
-    self.items.iter()
+  fn coord(&self, index: usize) -> Coord {
+    let col = (index % self.width) as isize;
+    let row = (index / self.width) as isize;
+    (self.origin.0 + col, self.origin.1 + row)
   }
 
-  fn mark_extents(&mut self, x: isize, y: isize) {
-    let [top, bottom, left, right] = self.extents;
+  fn grow_to_include(&mut self, loc: Coord) {
+    let area = if self.cells.is_empty() {
+      Bounds::new(loc, loc)
+    } else {
+      let far_corner = (
+        self.origin.0 + self.width as isize - 1,
+        self.origin.1 + self.height as isize - 1,
+      );
+      Bounds::new(self.origin, far_corner).including(loc)
+    };
+
+    let mut grown = Self {
+      cells: vec![None; area.width() * area.height()],
+      origin: area.min,
+      width: area.width(),
+      height: area.height(),
+      extent: self.extent,
+      edges: self.edges.clone(),
+    };
 
-    self.extents[0] = cmp::max(top, y);
-    self.extents[1] = cmp::max(right, x);
-    self.extents[2] = cmp::min(bottom, y);
-    self.extents[3] = cmp::min(left, x);
+    let cells = std::mem::take(&mut self.cells);
+    for (index, cell) in cells.into_iter().enumerate() {
+      if cell.is_some() {
+        let new_index = grown.index(self.coord(index)).unwrap();
+        grown.cells[new_index] = cell;
+      }
+    }
+
+    *self = grown;
+  }
+}
+
+impl<V: Clone> GridStorage<V> for DenseGrid<V> {
+  fn with_size(width: usize, height: usize) -> Self {
+    Self {
+      cells: vec![None; width * height],
+      origin: (0, 0),
+      width,
+      height,
+      extent: None,
+      edges: Default::default(),
+    }
+  }
+
+  fn get<T>(&self, loc: T) -> Option<&V>
+  where
+    T: Borrow<Coord>,
+  {
+    match self.edges.resolve(*loc.borrow(), self.extent) {
+      Lookup::Cell(loc) => self.index(loc).and_then(|index| self.cells[index].as_ref()),
+      Lookup::Default => self.edges.default.as_ref(),
+      Lookup::Outside => None,
+    }
+  }
+
+  fn insert(&mut self, loc: Coord, value: V) {
+    mark_extent(&mut self.extent, loc);
+
+    if self.index(loc).is_none() {
+      self.grow_to_include(loc);
+    }
+
+    let index = self.index(loc).unwrap();
+    self.cells[index] = Some(value);
+  }
+
+  fn iter(&self) -> Box<dyn Iterator<Item = (Coord, &V)> + '_> {
+    Box::new(
+      self
+        .cells
+        .iter()
+        .enumerate()
+        .filter_map(move |(index, cell)| cell.as_ref().map(|value| (self.coord(index), value))),
+    )
+  }
+
+  fn iter_mut(&mut self) -> Box<dyn Iterator<Item = (Coord, &mut V)> + '_> {
+    let (origin, width) = (self.origin, self.width as isize);
+    Box::new(
+      self
+        .cells
+        .iter_mut()
+        .enumerate()
+        .filter_map(move |(index, cell)| {
+          let index = index as isize;
+          let loc = (origin.0 + index % width, origin.1 + index / width);
+          cell.as_mut().map(|value| (loc, value))
+        }),
+    )
+  }
+
+  fn extent(&self) -> Option<Bounds> {
+    self.extent
+  }
+
+  fn set_boundary(&mut self, axis: usize, boundary: Boundary) {
+    self.edges.set(axis, boundary);
+  }
+
+  fn set_default(&mut self, value: V) {
+    self.edges.default = Some(value);
+  }
+
+  fn default_value(&self) -> Option<&V> {
+    self.edges.default.as_ref()
+  }
+
+  fn locate(&self, loc: Coord) -> Option<Coord> {
+    self.edges.locate(loc, self.extent)
   }
 }
 
@@ -78,3 +919,334 @@ fn test_grid() {
   assert_eq!(g.get_extent(Extent::LEFT), 0);
   assert_eq!(g.get_extent(Extent::RIGHT), 3);
 }
+
+#[test]
+fn test_dense_grid() {
+  let mut g: DenseGrid<i32> = DenseGrid::with_size(3, 2);
+  assert_eq!(g.get((0, 0)), None);
+  assert_eq!(g.get((5, 5)), None);
+  g.insert((0, 0), 42);
+  g.insert((2, 1), 21);
+  assert_eq!(g.get((0, 0)), Some(&42));
+  assert_eq!(g.get((2, 1)), Some(&21));
+
+  // Inserting outside the allocated area grows the grid and keeps old values
+  g.insert((-2, 3), 7);
+  assert_eq!(g.get((-2, 3)), Some(&7));
+  assert_eq!(g.get((0, 0)), Some(&42));
+  assert_eq!(g.get((2, 1)), Some(&21));
+  assert_eq!(g.get((-1, 0)), None);
+
+  let items: Vec<_> = g.iter().collect();
+  assert_eq!(items, vec![((0, 0), &42), ((2, 1), &21), ((-2, 3), &7)]);
+
+  assert_eq!(g.extent(), Some(Bounds::new((-2, 0), (2, 3))));
+}
+
+#[test]
+fn test_bounds() {
+  let bounds = Bounds::new((2, -1), (-3, -4));
+  assert_eq!(bounds.min, (-3, -4));
+  assert_eq!(bounds.max, (2, -1));
+  assert_eq!(bounds.width(), 6);
+  assert_eq!(bounds.height(), 4);
+  assert!(bounds.contains((-3, -4)));
+  assert!(bounds.contains((2, -1)));
+  assert!(bounds.contains((0, -2)));
+  assert!(!bounds.contains((0, 0)));
+  assert!(!bounds.contains((3, -2)));
+
+  let grown = bounds.including((-5, 1));
+  assert_eq!(grown, Bounds::new((-5, -4), (2, 1)));
+  assert_eq!(grown.width(), 8);
+  assert_eq!(grown.height(), 6);
+
+  let mut g: Grid<char> = Grid::new();
+  assert_eq!(g.extent(), None);
+  g.insert((-2, -7), 'a');
+  g.insert((-5, -3), 'b');
+  assert_eq!(g.extent(), Some(Bounds::new((-5, -7), (-2, -3))));
+  assert_eq!(g.get_extent(Extent::TOP), -3);
+  assert_eq!(g.get_extent(Extent::RIGHT), -2);
+  assert_eq!(g.get_extent(Extent::BOTTOM), -7);
+  assert_eq!(g.get_extent(Extent::LEFT), -5);
+}
+
+#[test]
+fn test_parse_and_render() {
+  let map = "#..\n.#.\n..#\n#..\n";
+
+  let g: Grid<char> = Grid::from_str_with(map, RowOrder::YDown, Ok::<char, ()>).unwrap();
+  assert_eq!(g.get((0, 0)), Some(&'#'));
+  assert_eq!(g.get((2, 2)), Some(&'#'));
+  assert_eq!(g.get((0, 3)), Some(&'#'));
+  assert_eq!(g.extent(), Some(Bounds::new((0, 0), (2, 3))));
+  assert_eq!(g.to_string(), map);
+
+  let g: DenseGrid<bool> = DenseGrid::from_str_with(map, RowOrder::YUp, |c| match c {
+    '#' => Ok(true),
+    '.' => Ok(false),
+    _ => Err(format!("Bad character {}", c)),
+  })
+  .unwrap();
+  assert_eq!(g.get((0, 3)), Some(&true));
+  assert_eq!(g.get((0, 0)), Some(&true));
+  assert_eq!(g.get((2, 1)), Some(&true));
+  assert_eq!(g.get((2, 3)), Some(&false));
+  let render = |cell: Option<&bool>| match cell {
+    Some(true) => '#',
+    _ => '.',
+  };
+  assert_eq!(g.render(RowOrder::YUp, render), map);
+  assert_eq!(g.render(RowOrder::YDown, render), "#..\n..#\n.#.\n#..\n");
+
+  let bad: Result<DenseGrid<bool>, String> =
+    DenseGrid::from_str_with("#x", RowOrder::YDown, |c| match c {
+      '#' => Ok(true),
+      _ => Err(format!("Bad character {}", c)),
+    });
+  assert_eq!(bad.err(), Some("Bad character x".to_string()));
+}
+
+#[test]
+fn test_boundaries() {
+  let map = "ab\ncd\n";
+  let mut g: DenseGrid<char> =
+    DenseGrid::from_str_with(map, RowOrder::YDown, Ok::<char, ()>).unwrap();
+  assert_eq!(g.get((2, 0)), None);
+
+  g.set_boundary(0, Boundary::Wrap);
+  assert_eq!(g.get((2, 0)), Some(&'a'));
+  assert_eq!(g.get((-1, 1)), Some(&'d'));
+  assert_eq!(g.get((1_000_001, 0)), Some(&'b'));
+  assert_eq!(g.get((0, 2)), None);
+  assert_eq!(g.get((0, -1)), None);
+
+  g.set_boundary(1, Boundary::Wrap);
+  assert_eq!(g.get((-3, -3)), Some(&'d'));
+
+  let mut g: Grid<char> = Grid::from_str_with(map, RowOrder::YDown, Ok::<char, ()>).unwrap();
+  g.set_boundary(0, Boundary::Infinite);
+  g.set_boundary(1, Boundary::Wrap);
+  assert_eq!(g.get((5, 0)), None);
+  g.set_default('.');
+  assert_eq!(g.get((5, 0)), Some(&'.'));
+  assert_eq!(g.get((1, 3)), Some(&'d'));
+  assert_eq!(g.get((-1, 3)), Some(&'.'));
+}
+
+#[test]
+fn test_n_dimensional_grid() {
+  type Coord3 = (isize, isize, isize);
+
+  let mut g: Grid<char, Coord3> =
+    Grid::from_str_with(".#\n#.", RowOrder::YDown, Ok::<char, ()>).unwrap();
+  assert_eq!(g.get((1, 0, 0)), Some(&'#'));
+  assert_eq!(g.get((1, 0, 1)), None);
+
+  g.insert((-1, 3, 2), '@');
+  assert_eq!(g.extent(), Some(Bounds::new((-1, 0, 0), (1, 3, 2))));
+  assert_eq!(g.extent().unwrap().size(2), 3);
+  assert!(g.extent().unwrap().contains((0, 2, 1)));
+  assert!(!g.extent().unwrap().contains((0, 2, 3)));
+
+  g.set_boundary(2, Boundary::Wrap);
+  assert_eq!(g.get((-1, 3, -1)), Some(&'@'));
+  assert_eq!(g.get((0, 1, 3)), Some(&'#'));
+}
+
+#[test]
+fn test_rays() {
+  let map = "#..#\n....\n.#..\n";
+  let mut g: DenseGrid<char> =
+    DenseGrid::from_str_with(map, RowOrder::YDown, Ok::<char, ()>).unwrap();
+
+  // Direction::S is -y, which is up the page for a y-down map
+  let ray: Vec<_> = g.ray((1, 2), Direction::S).collect();
+  assert_eq!(ray, vec![(1, 1), (1, 0)]);
+  assert_eq!(g.ray((0, 0), Direction::W).count(), 0);
+  assert_eq!(g.ray((0, 2), Direction::NE).count(), 0);
+  assert_eq!(g.ray((0, 0), Direction::E).count(), 3);
+
+  // Wrapping boundaries still stop at the edge
+  g.set_boundary(0, Boundary::Wrap);
+  assert_eq!(g.ray((0, 0), Direction::E).count(), 3);
+
+  let is_wall = |&c: &char| c == '#';
+  assert_eq!(
+    g.first_visible((0, 0), Direction::E, is_wall),
+    Some(((3, 0), &'#'))
+  );
+  assert_eq!(g.first_visible((0, 1), Direction::E, is_wall), None);
+  assert_eq!(g.first_visible((3, 1), Direction::SW, is_wall), None);
+  assert_eq!(
+    g.first_visible((3, 0), Direction::NW, is_wall),
+    Some(((1, 2), &'#'))
+  );
+}
+
+#[test]
+fn test_regions() {
+  let map = "##..#\n#..##\n..#..\n#...#\n";
+  let g: Grid<char> = Grid::from_str_with(map, RowOrder::YDown, Ok::<char, ()>).unwrap();
+  let is_wall = |&c: &char| c == '#';
+
+  let corner = g
+    .flood_fill((0, 0), Neighborhood::VonNeumann, is_wall)
+    .unwrap();
+  assert_eq!(corner.cells, vec![(0, 0), (1, 0), (0, 1)]);
+  assert_eq!(corner.size(), 3);
+  assert_eq!(corner.bounds, Bounds::new((0, 0), (1, 1)));
+  assert_eq!(
+    g.flood_fill((2, 0), Neighborhood::VonNeumann, is_wall),
+    None
+  );
+
+  let walls = g.components(Neighborhood::VonNeumann, is_wall);
+  let sizes: Vec<_> = walls.iter().map(|r| (r.label, r.size())).collect();
+  assert_eq!(sizes, vec![(0, 3), (1, 3), (2, 1), (3, 1), (4, 1)]);
+  assert_eq!(walls[1].bounds, Bounds::new((3, 1), (4, 0)));
+  assert_eq!(walls[1].cells, vec![(4, 0), (3, 1), (4, 1)]);
+
+  // A diagonal step joins the centre wall to the top right corner
+  let walls = g.components(Neighborhood::Moore, is_wall);
+  let sizes: Vec<_> = walls.iter().map(|r| r.size()).collect();
+  assert_eq!(sizes, vec![3, 4, 1, 1]);
+  assert_eq!(walls[1].bounds, Bounds::new((2, 0), (4, 2)));
+
+  let floor = g.components(Neighborhood::VonNeumann, |&c| c == '.');
+  assert_eq!(floor.len(), 1);
+  assert_eq!(floor[0].size(), 11);
+
+  let mut wrapped = g.clone();
+  wrapped.set_boundary(0, Boundary::Wrap);
+  assert_eq!(
+    wrapped.components(Neighborhood::VonNeumann, is_wall).len(),
+    5
+  );
+}
+
+#[test]
+fn test_transformations() {
+  let g: Grid<char> = Grid::from_str_with("abc\ndef", RowOrder::YDown, Ok::<char, ()>).unwrap();
+
+  assert_eq!(g.rotate_cw().to_string(), "da\neb\nfc\n");
+  assert_eq!(g.flip_h().to_string(), "cba\nfed\n");
+  assert_eq!(g.flip_v().to_string(), "def\nabc\n");
+  assert_eq!(g.transpose().to_string(), "ad\nbe\ncf\n");
+  assert_eq!(g.rotate_cw().extent(), Some(Bounds::new((0, 0), (1, 2))));
+
+  let mut turned = g.clone();
+  for _ in 0..4 {
+    turned = turned.rotate_cw();
+  }
+  assert_eq!(turned.to_string(), g.to_string());
+
+  let orientations: Vec<String> = g.all_orientations().iter().map(|o| o.to_string()).collect();
+  assert_eq!(orientations.len(), 8);
+  assert_eq!(orientations.iter().collect::<HashSet<_>>().len(), 8);
+  assert_eq!(orientations[0], g.to_string());
+  assert!(orientations.contains(&g.transpose().to_string()));
+  assert!(orientations.contains(&g.flip_v().to_string()));
+
+  let [top, right, bottom, left] = g.edges();
+  assert_eq!(top, vec!['a', 'b', 'c']);
+  assert_eq!(right, vec!['c', 'f']);
+  assert_eq!(bottom, vec!['d', 'e', 'f']);
+  assert_eq!(left, vec!['a', 'd']);
+  assert_eq!(g.flip_h().edges()[3], right);
+
+  let sub = g.subgrid(Bounds::new((1, 0), (2, 1)));
+  assert_eq!(sub.to_string(), "bc\nef\n");
+  assert_eq!(sub.get((0, 0)), None);
+  assert_eq!(sub.get((1, 1)), Some(&'e'));
+
+  // Transforming an offset grid keeps its top-left corner in place
+  let moved = g.subgrid(Bounds::new((1, 0), (2, 1))).rotate_cw();
+  assert_eq!(moved.to_string(), "eb\nfc\n");
+  assert_eq!(moved.extent(), Some(Bounds::new((1, 0), (2, 1))));
+}
+
+#[test]
+fn test_find_pattern() {
+  let parse = |map| Grid::from_str_with(map, RowOrder::YDown, Ok::<char, ()>).unwrap();
+
+  let image: Grid<char> = parse(".##.#\n###.#\n.#...\n....#");
+  let plus: Grid<char> = parse(".#.\n###\n.#.");
+  let found = image.find_pattern(&plus, Some(&'.'));
+  assert_eq!(found.len(), 1);
+  assert_eq!(found[0].offset, (0, 0));
+  assert_eq!(found[0].cells.len(), 5);
+  // Without a wildcard the floor has to match too
+  assert_eq!(image.find_pattern(&plus, None), vec![]);
+  // A symmetric pattern only matches once across all orientations
+  assert_eq!(image.find_pattern_any_orientation(&plus, Some(&'.')), found);
+
+  let image: Grid<char> = parse("##...\n#....\n...#.\n..##.");
+  let corner: Grid<char> = parse("##\n#.");
+  assert_eq!(image.find_pattern(&corner, Some(&'.')).len(), 1);
+  let found = image.find_pattern_any_orientation(&corner, Some(&'.'));
+  let placements: Vec<_> = found.iter().map(|m| (m.offset, m.orientation)).collect();
+  assert_eq!(placements, vec![((0, 0), 0), ((2, 2), 2)]);
+
+  let mut marked = image.clone();
+  marked.mark(&found, 'O');
+  assert_eq!(marked.to_string(), "OO...\nO....\n...O.\n..OO.\n");
+
+  // Patterns larger than the grid never match
+  assert_eq!(corner.find_pattern(&image, None), vec![]);
+}
+
+#[test]
+fn test_iteration_order() {
+  let mut g: Grid<i32> = Grid::new();
+  for &(loc, value) in [
+    ((1, 1), 4),
+    ((0, 0), 1),
+    ((0, 1), 3),
+    ((1, 0), 2),
+    ((2, 1), 5),
+  ]
+  .iter()
+  {
+    g.insert(loc, value);
+  }
+  let mut dense: DenseGrid<i32> = DenseGrid::new();
+  for (loc, &value) in g.iter() {
+    dense.insert(loc, value);
+  }
+
+  let values = |cells: Vec<(Coord, &i32)>| cells.into_iter().map(|(_, &v)| v).collect::<Vec<_>>();
+  assert_eq!(values(g.iter().collect()), vec![1, 2, 3, 4, 5]);
+  assert_eq!(values(dense.iter().collect()), vec![1, 2, 3, 4, 5]);
+  assert_eq!(values(g.iter_column_major().collect()), vec![1, 3, 2, 4, 5]);
+  assert_eq!(
+    values(dense.iter_column_major().collect()),
+    vec![1, 3, 2, 4, 5]
+  );
+
+  let rows: Vec<_> = g.rows().map(values).collect();
+  assert_eq!(rows, vec![vec![1, 2], vec![3, 4, 5]]);
+  let cols: Vec<_> = dense.cols().map(values).collect();
+  assert_eq!(cols, vec![vec![1, 3], vec![2, 4], vec![5]]);
+
+  for (loc, value) in g.iter_mut() {
+    *value += 10 * loc.0 as i32;
+  }
+  for (_, value) in dense.iter_mut() {
+    *value *= 2;
+  }
+  assert_eq!(values(g.iter().collect()), vec![1, 12, 3, 14, 25]);
+  assert_eq!(values(dense.iter().collect()), vec![2, 4, 6, 8, 10]);
+  assert_eq!(g.to_string(), "112 \n31425\n");
+
+  let mut cube: Grid<i32, (isize, isize, isize)> = Grid::new();
+  cube.insert((0, 0, 1), 3);
+  cube.insert((1, 0, 0), 2);
+  cube.insert((0, 0, 0), 1);
+  assert_eq!(cube.rows().count(), 2);
+  assert_eq!(
+    cube.iter().map(|(_, &v)| v).collect::<Vec<_>>(),
+    vec![1, 2, 3]
+  );
+}
@@ -0,0 +1,242 @@
+use super::coords::{Coordinate, NeighborIter, Neighborhood};
+use std::str::FromStr;
+use strum_macros::EnumIter;
+
+// A cell on a grid of pointy-topped hexagons in axial coordinates. East is +q
+// and south-east is +r; the third cube coordinate, `s`, is implied by
+// q + r + s = 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[allow(dead_code)]
+pub struct Hex {
+  pub q: isize,
+  pub r: isize,
+}
+
+#[allow(dead_code)]
+const HEX_OFFSETS: [Hex; 6] = [
+  Hex { q: 1, r: 0 },
+  Hex { q: -1, r: 0 },
+  Hex { q: 1, r: -1 },
+  Hex { q: 0, r: -1 },
+  Hex { q: 0, r: 1 },
+  Hex { q: -1, r: 1 },
+];
+
+// The cells two steps away that sit between two neighbors, touching both at a
+// corner
+#[allow(dead_code)]
+const HEX_DIAGONALS: [Hex; 6] = [
+  Hex { q: 2, r: -1 },
+  Hex { q: -2, r: 1 },
+  Hex { q: 1, r: -2 },
+  Hex { q: -1, r: 2 },
+  Hex { q: 1, r: 1 },
+  Hex { q: -1, r: -1 },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+#[allow(dead_code)]
+pub enum HexDirection {
+  E,
+  W,
+  NE,
+  NW,
+  SE,
+  SW,
+}
+
+impl HexDirection {
+  #[allow(dead_code)]
+  pub fn delta(&self) -> Hex {
+    match self {
+      HexDirection::E => HEX_OFFSETS[0],
+      HexDirection::W => HEX_OFFSETS[1],
+      HexDirection::NE => HEX_OFFSETS[2],
+      HexDirection::NW => HEX_OFFSETS[3],
+      HexDirection::SE => HEX_OFFSETS[4],
+      HexDirection::SW => HEX_OFFSETS[5],
+    }
+  }
+
+  // Splits a run of directions with no separators, like "esenee"
+  #[allow(dead_code)]
+  pub fn parse_path(s: &str) -> Result<Vec<HexDirection>, String> {
+    let mut path = vec![];
+    let mut rest = s.trim();
+
+    while !rest.is_empty() {
+      let len = if rest.starts_with('n') || rest.starts_with('s') {
+        2
+      } else {
+        1
+      };
+      let part = rest
+        .get(..len)
+        .ok_or_else(|| format!("Incomplete hex direction at end of {}", s))?;
+      path.push(part.parse()?);
+      rest = &rest[len..];
+    }
+
+    Ok(path)
+  }
+}
+
+impl FromStr for HexDirection {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "e" => Ok(HexDirection::E),
+      "w" => Ok(HexDirection::W),
+      "ne" => Ok(HexDirection::NE),
+      "nw" => Ok(HexDirection::NW),
+      "se" => Ok(HexDirection::SE),
+      "sw" => Ok(HexDirection::SW),
+      _ => Err(format!("Could not parse hex direction: {}", s)),
+    }
+  }
+}
+
+impl Hex {
+  #[allow(dead_code)]
+  pub fn new(q: isize, r: isize) -> Self {
+    Self { q, r }
+  }
+
+  #[allow(dead_code)]
+  pub fn from_cube(x: isize, y: isize, z: isize) -> Option<Self> {
+    match x + y + z {
+      0 => Some(Self::new(x, z)),
+      _ => None,
+    }
+  }
+
+  #[allow(dead_code)]
+  pub fn s(&self) -> isize {
+    -self.q - self.r
+  }
+
+  #[allow(dead_code)]
+  pub fn cube(&self) -> (isize, isize, isize) {
+    (self.q, self.s(), self.r)
+  }
+
+  #[allow(dead_code)]
+  pub fn step(&self, direction: HexDirection) -> Self {
+    let delta = direction.delta();
+    Self::new(self.q + delta.q, self.r + delta.r)
+  }
+
+  #[allow(dead_code)]
+  pub fn walk(&self, path: &[HexDirection]) -> Self {
+    path
+      .iter()
+      .fold(*self, |hex, &direction| hex.step(direction))
+  }
+
+  #[allow(dead_code)]
+  pub fn distance(&self, other: &Hex) -> usize {
+    let dq = (self.q - other.q).abs();
+    let dr = (self.r - other.r).abs();
+    let ds = (self.s() - other.s()).abs();
+    ((dq + dr + ds) / 2) as usize
+  }
+}
+
+impl Coordinate for Hex {
+  const DIMENSIONS: usize = 2;
+
+  fn component(&self, axis: usize) -> isize {
+    match axis {
+      0 => self.q,
+      1 => self.r,
+      _ => panic!("No axis {} in a hex coordinate", axis),
+    }
+  }
+
+  fn map_components<F>(&self, mut f: F) -> Self
+  where
+    F: FnMut(usize, isize) -> isize,
+  {
+    Self::new(f(0, self.q), f(1, self.r))
+  }
+
+  // Every neighbor of a hex shares an edge with it, so the von Neumann and
+  // Moore neighborhoods are the same six cells
+  fn neighbors_with<'a>(&self, kind: Neighborhood<'a, Self>) -> NeighborIter<'a, Self> {
+    let offsets = match kind {
+      Neighborhood::VonNeumann | Neighborhood::Moore => &HEX_OFFSETS[..],
+      Neighborhood::Diagonal => &HEX_DIAGONALS[..],
+      Neighborhood::Custom(offsets) => offsets,
+    };
+    NeighborIter::new(*self, Neighborhood::Custom(offsets))
+  }
+}
+
+#[test]
+fn test_hex_paths() {
+  use strum::IntoEnumIterator;
+
+  let path = HexDirection::parse_path("esenee").unwrap();
+  assert_eq!(
+    path,
+    vec![
+      HexDirection::E,
+      HexDirection::SE,
+      HexDirection::NE,
+      HexDirection::E
+    ]
+  );
+
+  let origin = Hex::default();
+  assert_eq!(origin.walk(&path), Hex::new(3, 0));
+  let esew = HexDirection::parse_path("esew").unwrap();
+  assert_eq!(origin.walk(&esew), origin.step(HexDirection::SE));
+  let nwwswee = HexDirection::parse_path("nwwswee").unwrap();
+  assert_eq!(origin.walk(&nwwswee), origin);
+
+  assert!(HexDirection::parse_path("nex").is_err());
+  assert!(HexDirection::parse_path("en").is_err());
+
+  for dir in HexDirection::iter() {
+    assert_eq!(origin.step(dir).distance(&origin), 1);
+  }
+}
+
+#[test]
+fn test_hex_coords() {
+  use super::grid::{Grid, GridStorage};
+
+  let hex = Hex::new(2, -1);
+  assert_eq!(hex.cube(), (2, -1, -1));
+  assert_eq!(Hex::from_cube(2, -1, -1), Some(hex));
+  assert_eq!(Hex::from_cube(2, -1, 0), None);
+
+  let neighbors: Vec<_> = hex.neighbors().collect();
+  assert_eq!(neighbors.len(), 6);
+  assert!(neighbors.contains(&hex.step(HexDirection::NW)));
+  assert!(neighbors.iter().all(|n| n.distance(&hex) == 1));
+  let adjacent: Vec<_> = hex.neighbors_with(Neighborhood::VonNeumann).collect();
+  assert_eq!(adjacent, neighbors);
+  let diagonals: Vec<_> = hex.neighbors_with(Neighborhood::Diagonal).collect();
+  assert_eq!(diagonals.len(), 6);
+  assert!(diagonals.iter().all(|n| n.distance(&hex) == 2));
+  assert!(diagonals
+    .iter()
+    .all(|d| neighbors.iter().filter(|n| n.distance(d) == 1).count() == 2));
+
+  assert_eq!(Hex::new(0, 0).distance(&Hex::new(3, -5)), 5);
+  assert_eq!(Hex::new(-2, 4).distance(&Hex::new(1, 1)), 3);
+
+  let mut tiles: Grid<bool, Hex> = Grid::new();
+  tiles.insert(
+    Hex::default().walk(&HexDirection::parse_path("sesenw").unwrap()),
+    true,
+  );
+  assert_eq!(tiles.get(Hex::new(0, 1)), Some(&true));
+  let flipped_neighbors = Hex::default()
+    .neighbors()
+    .filter(|n| tiles.get(n) == Some(&true))
+    .count();
+  assert_eq!(flipped_neighbors, 1);
+}
@@ -0,0 +1,276 @@
+use super::coords::{Direction, Planar};
+use super::grid::GridStorage;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::hash::Hash;
+use std::marker::PhantomData;
+use strum::IntoEnumIterator;
+
+#[allow(dead_code)]
+pub trait Graph {
+  type Node: Clone + Eq + Hash + Ord;
+
+  // Each node reachable in one step from `node`, with the cost of that step
+  fn neighbors(&self, node: &Self::Node) -> Vec<(Self::Node, usize)>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(dead_code)]
+pub struct Path<N> {
+  pub nodes: Vec<N>,
+  pub cost: usize,
+}
+
+impl<N> Path<N> {
+  #[allow(dead_code)]
+  pub fn steps(&self) -> usize {
+    self.nodes.len() - 1
+  }
+}
+
+// Fewest steps, ignoring step costs. The path's cost is its number of steps.
+#[allow(dead_code)]
+pub fn bfs<G, F>(graph: &G, start: G::Node, is_goal: F) -> Option<Path<G::Node>>
+where
+  G: Graph,
+  F: Fn(&G::Node) -> bool,
+{
+  let mut came_from: HashMap<G::Node, G::Node> = HashMap::new();
+  let mut queue = VecDeque::new();
+  came_from.insert(start.clone(), start.clone());
+  queue.push_back(start.clone());
+
+  while let Some(node) = queue.pop_front() {
+    if is_goal(&node) {
+      let nodes = trace_back(&came_from, &start, node);
+      let cost = nodes.len() - 1;
+      return Some(Path { nodes, cost });
+    }
+
+    for (next, _) in graph.neighbors(&node) {
+      if !came_from.contains_key(&next) {
+        came_from.insert(next.clone(), node.clone());
+        queue.push_back(next);
+      }
+    }
+  }
+
+  None
+}
+
+#[allow(dead_code)]
+pub fn dijkstra<G, F>(graph: &G, start: G::Node, is_goal: F) -> Option<Path<G::Node>>
+where
+  G: Graph,
+  F: Fn(&G::Node) -> bool,
+{
+  astar(graph, start, is_goal, |_| 0)
+}
+
+// `heuristic` must never overestimate the remaining cost, or the path found
+// may not be the cheapest
+#[allow(dead_code)]
+pub fn astar<G, F, H>(graph: &G, start: G::Node, is_goal: F, heuristic: H) -> Option<Path<G::Node>>
+where
+  G: Graph,
+  F: Fn(&G::Node) -> bool,
+  H: Fn(&G::Node) -> usize,
+{
+  let mut came_from: HashMap<G::Node, G::Node> = HashMap::new();
+  let mut best_cost: HashMap<G::Node, usize> = HashMap::new();
+  let mut frontier = BinaryHeap::new();
+  came_from.insert(start.clone(), start.clone());
+  best_cost.insert(start.clone(), 0);
+  frontier.push(Reverse((heuristic(&start), 0, start.clone())));
+
+  while let Some(Reverse((_, cost, node))) = frontier.pop() {
+    if cost > best_cost[&node] {
+      continue;
+    }
+
+    if is_goal(&node) {
+      let nodes = trace_back(&came_from, &start, node);
+      return Some(Path { nodes, cost });
+    }
+
+    for (next, step_cost) in graph.neighbors(&node) {
+      let next_cost = cost + step_cost;
+      if best_cost.get(&next).is_none_or(|&known| next_cost < known) {
+        best_cost.insert(next.clone(), next_cost);
+        came_from.insert(next.clone(), node.clone());
+        frontier.push(Reverse((next_cost + heuristic(&next), next_cost, next)));
+      }
+    }
+  }
+
+  None
+}
+
+#[allow(dead_code)]
+fn trace_back<N: Clone + Eq + Hash>(came_from: &HashMap<N, N>, start: &N, end: N) -> Vec<N> {
+  let mut nodes = vec![end];
+  while nodes.last().unwrap() != start {
+    let previous = came_from[nodes.last().unwrap()].clone();
+    nodes.push(previous);
+  }
+  nodes.reverse();
+
+  nodes
+}
+
+// Moves between adjacent cells of a grid that `passable` allows, at a cost of
+// one per step. Only the four cardinal directions are used unless diagonals
+// are turned on. Stepping over a wrapping edge lands on the opposite edge;
+// nothing past any other edge is ever visited.
+#[allow(dead_code)]
+pub struct GridGraph<'a, G, V, C, F> {
+  grid: &'a G,
+  passable: F,
+  directions: Vec<Direction>,
+  value: PhantomData<(V, C)>,
+}
+
+impl<'a, G, V, C, F> GridGraph<'a, G, V, C, F>
+where
+  G: GridStorage<V, C>,
+  C: Planar,
+  F: Fn(&V) -> bool,
+{
+  #[allow(dead_code)]
+  pub fn new(grid: &'a G, passable: F) -> Self {
+    Self {
+      grid,
+      passable,
+      directions: Direction::all_cardinal().to_vec(),
+      value: PhantomData,
+    }
+  }
+
+  #[allow(dead_code)]
+  pub fn with_diagonals(mut self) -> Self {
+    self.directions = Direction::iter().collect();
+    self
+  }
+}
+
+impl<'a, G, V, C, F> Graph for GridGraph<'a, G, V, C, F>
+where
+  G: GridStorage<V, C>,
+  C: Planar,
+  F: Fn(&V) -> bool,
+{
+  type Node = C;
+
+  fn neighbors(&self, node: &C) -> Vec<(C, usize)> {
+    self
+      .directions
+      .iter()
+      .map(|dir| {
+        let (dx, dy) = dir.delta();
+        C::from_xy(node.component(0) + dx, node.component(1) + dy)
+      })
+      .filter_map(|next| self.grid.locate(next))
+      .filter(|next| self.grid.get(next).is_some_and(|v| (self.passable)(v)))
+      .map(|next| (next, 1))
+      .collect()
+  }
+}
+
+#[test]
+fn test_grid_search() {
+  use super::coords::Point;
+  use super::grid::{DenseGrid, RowOrder};
+
+  let input = super::common::get_input("11_sample").unwrap();
+  let seats: DenseGrid<char> =
+    DenseGrid::from_str_with(&input, RowOrder::YDown, Ok::<char, ()>).unwrap();
+  let graph = GridGraph::new(&seats, |&c| c == 'L');
+
+  let start = (0, 0);
+  let goal = (9, 9);
+  let shortest = bfs(&graph, start, |&c| c == goal).unwrap();
+  assert_eq!(shortest.steps(), 18);
+  assert_eq!(shortest.nodes.first(), Some(&start));
+  assert_eq!(shortest.nodes.last(), Some(&goal));
+  for pair in shortest.nodes.windows(2) {
+    assert_eq!(Point::from(pair[0]).manhattan(pair[1].into()), 1);
+    assert_eq!(seats.get(pair[1]), Some(&'L'));
+  }
+
+  let cheapest = dijkstra(&graph, start, |&c| c == goal).unwrap();
+  assert_eq!(cheapest.cost, 18);
+  let guided = astar(
+    &graph,
+    start,
+    |&c| c == goal,
+    |&c| Point::from(c).manhattan(goal.into()) as usize,
+  )
+  .unwrap();
+  assert_eq!(guided.cost, 18);
+  assert_eq!(guided.nodes.len(), shortest.nodes.len());
+
+  // The seat at (1, 0) is floor, so the seat at (2, 0) is only reachable the
+  // long way round without diagonals
+  assert_eq!(bfs(&graph, start, |&c| c == (2, 0)).unwrap().steps(), 4);
+  let diagonal = GridGraph::new(&seats, |&c| c == 'L').with_diagonals();
+  assert_eq!(bfs(&diagonal, start, |&c| c == (2, 0)).unwrap().steps(), 2);
+
+  // Floor tiles are never passable
+  assert_eq!(bfs(&graph, start, |&c| c == (1, 0)), None);
+}
+
+#[test]
+fn test_wrapped_grid_search() {
+  use super::grid::{Boundary, DenseGrid, RowOrder};
+
+  let mut tiles: DenseGrid<char> =
+    DenseGrid::from_str_with("#.#\n...\n...", RowOrder::YDown, Ok::<char, ()>).unwrap();
+  tiles.set_boundary(0, Boundary::Wrap);
+  let graph = GridGraph::new(&tiles, |&c| c == '#');
+
+  // Stepping west off the left edge lands on the right edge
+  let wrapped = bfs(&graph, (0, 0), |&c| c == (2, 0)).unwrap();
+  assert_eq!(wrapped.nodes, vec![(0, 0), (2, 0)]);
+  // Running out of cells ends the search instead of walking off forever
+  assert_eq!(bfs(&graph, (0, 0), |_| false), None);
+  assert_eq!(dijkstra(&graph, (0, 0), |_| false), None);
+
+  // Past an infinite edge every cell reads as passable, but only the cells
+  // inside the grid are searched
+  tiles.set_boundary(1, Boundary::Infinite);
+  tiles.set_default('#');
+  let graph = GridGraph::new(&tiles, |&c| c == '#');
+  assert_eq!(bfs(&graph, (0, 0), |_| false), None);
+}
+
+#[test]
+fn test_weighted_search() {
+  struct Roads(HashMap<&'static str, Vec<(&'static str, usize)>>);
+
+  impl Graph for Roads {
+    type Node = &'static str;
+
+    fn neighbors(&self, node: &&'static str) -> Vec<(&'static str, usize)> {
+      self.0.get(node).cloned().unwrap_or_default()
+    }
+  }
+
+  let mut roads = HashMap::new();
+  roads.insert("home", vec![("bridge", 10), ("ferry", 2)]);
+  roads.insert("ferry", vec![("island", 3)]);
+  roads.insert("island", vec![("bridge", 1)]);
+  roads.insert("bridge", vec![("work", 1)]);
+  let roads = Roads(roads);
+
+  let fewest_stops = bfs(&roads, "home", |&n| n == "work").unwrap();
+  assert_eq!(fewest_stops.nodes, vec!["home", "bridge", "work"]);
+
+  let cheapest = dijkstra(&roads, "home", |&n| n == "work").unwrap();
+  assert_eq!(
+    cheapest.nodes,
+    vec!["home", "ferry", "island", "bridge", "work"]
+  );
+  assert_eq!(cheapest.cost, 7);
+
+  assert_eq!(dijkstra(&roads, "work", |&n| n == "home"), None);
+}